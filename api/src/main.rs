@@ -7,14 +7,20 @@ use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
+    market::list_markets,
     openapi::ApiDoc,
     order::{best_ask, best_bid, list_orders, spread},
+    order_event::list_order_events,
+    status::list_statuses,
     trade::list_trades,
 };
 
 mod db;
+mod market;
 mod openapi;
 mod order;
+mod order_event;
+mod status;
 mod trade;
 
 #[derive(Clone)]
@@ -45,7 +51,10 @@ pub async fn serve(state: AppState) {
         .route("/orders/spread", get(spread))
         .route("/orders/best-bid", get(best_bid))
         .route("/orders/best-ask", get(best_ask))
+        .route("/orders/events", get(list_order_events))
         .route("/trades/list", get(list_trades))
+        .route("/status", get(list_statuses))
+        .route("/markets", get(list_markets))
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .with_state(state);
 
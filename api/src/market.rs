@@ -0,0 +1,64 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use sea_orm::DatabaseConnection;
+use serde::Deserialize;
+use sparker_core::{repo::market, AmountFormat, Market};
+use utoipa::IntoParams;
+
+use crate::{internal_error, AppState};
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListMarketsParams {
+    /// Returns only the metadata of the market.
+    market_id: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/markets",
+    params(
+        ListMarketsParams,
+    ),
+    responses(
+        (status = 200, description = "Returns the assets, decimals and price precision of the markets", body = Vec<Market>)
+    )
+)]
+pub async fn list_markets(
+    Query(ListMarketsParams { market_id }): Query<ListMarketsParams>,
+    State(AppState { db_conn, .. }): State<AppState>,
+) -> Result<Json<Vec<Market>>, (StatusCode, String)> {
+    let res = market::Query::find(&db_conn, market_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(res))
+}
+
+/// Finds the metadata of a market to format its amounts and prices, if they are requested as
+/// decimals.
+///
+/// # Errors
+///
+/// Fails with `404 Not Found` if the decimal format is requested for a market without metadata.
+pub async fn find_format_market(
+    db_conn: &DatabaseConnection,
+    market_id: &str,
+    format: Option<AmountFormat>,
+) -> Result<Option<Market>, (StatusCode, String)> {
+    if format.unwrap_or_default() == AmountFormat::Raw {
+        return Ok(None);
+    }
+
+    let market = market::Query::find_by_id(db_conn, market_id)
+        .await
+        .map_err(internal_error)?;
+    market.map(Some).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            format!("No metadata for market {}", market_id),
+        )
+    })
+}
@@ -1,13 +1,16 @@
 use utoipa::OpenApi;
 
-use super::{order, trade};
+use super::{market, order, order_event, status, trade};
 
 #[derive(OpenApi)]
 #[openapi(paths(
+    market::list_markets,
     order::list_orders,
     order::spread,
     order::best_bid,
     order::best_ask,
+    order_event::list_order_events,
+    status::list_statuses,
     trade::list_trades
 ))]
 pub struct ApiDoc;
@@ -4,10 +4,10 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
-use sparker_core::{repo::order, Order, OrderType};
+use sparker_core::{repo::order, AmountFormat, Order, OrderType};
 use utoipa::{IntoParams, ToSchema};
 
-use crate::{internal_error, AppState};
+use crate::{internal_error, market::find_format_market, AppState};
 
 #[derive(Serialize, Deserialize, ToSchema)]
 pub struct Spread {
@@ -98,6 +98,8 @@ pub struct ListOrdersParams {
     limit: Option<u64>,
     offset: Option<u64>,
     user_ne: Option<String>,
+    /// Adds the amounts and prices as decimal strings with `decimal`.
+    format: Option<AmountFormat>,
 }
 
 #[utoipa::path(
@@ -117,11 +119,13 @@ pub async fn list_orders(
         limit,
         offset,
         user_ne,
+        format,
     }): Query<ListOrdersParams>,
     State(AppState { db_conn, .. }): State<AppState>,
 ) -> Result<Json<Vec<Order>>, (StatusCode, String)> {
     let limit = limit.unwrap_or(50);
     let offset = offset.unwrap_or(0);
+    let market = find_format_market(&db_conn, &market_id, format).await?;
 
     let res = match order_type {
         Some(order_type) => {
@@ -132,5 +136,13 @@ pub async fn list_orders(
     }
     .map_err(internal_error)?;
 
+    let res = match market {
+        Some(market) => res
+            .into_iter()
+            .map(|order| order.with_decimals(&market))
+            .collect(),
+        None => res,
+    };
+
     Ok(Json(res))
 }
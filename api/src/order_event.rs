@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use sparker_core::{repo::order_event, OrderEvent};
+use utoipa::IntoParams;
+
+use crate::{internal_error, AppState};
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListOrderEventsParams {
+    /// Returns the transitions of the order, oldest first.
+    order_id: Option<String>,
+    /// Returns the latest transitions of the market's orders, newest first.
+    market_id: Option<String>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/events",
+    params(
+        ListOrderEventsParams,
+    ),
+    responses(
+        (status = 200, description = "Returns list of order state transitions", body = Vec<OrderEvent>),
+        (status = 400, description = "Neither order_id nor market_id is given")
+    )
+)]
+pub async fn list_order_events(
+    Query(ListOrderEventsParams {
+        order_id,
+        market_id,
+        limit,
+        offset,
+    }): Query<ListOrderEventsParams>,
+    State(AppState { db_conn, .. }): State<AppState>,
+) -> Result<Json<Vec<OrderEvent>>, (StatusCode, String)> {
+    let limit = limit.unwrap_or(50);
+    let offset = offset.unwrap_or(0);
+
+    let res = match (order_id, market_id) {
+        (Some(order_id), _) => {
+            order_event::Query::find_by_order_id(&db_conn, order_id, limit, offset).await
+        }
+        (None, Some(market_id)) => {
+            order_event::Query::find(&db_conn, market_id, limit, offset).await
+        }
+        (None, None) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Either order_id or market_id is required".to_string(),
+            ))
+        }
+    }
+    .map_err(internal_error)?;
+
+    Ok(Json(res))
+}
@@ -0,0 +1,37 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use sparker_core::{repo::state, MarketStatus};
+use utoipa::IntoParams;
+
+use crate::{internal_error, AppState};
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListStatusesParams {
+    /// Returns only the status of the market.
+    market_id: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/status",
+    params(
+        ListStatusesParams,
+    ),
+    responses(
+        (status = 200, description = "Returns the indexing status of the markets", body = Vec<MarketStatus>)
+    )
+)]
+pub async fn list_statuses(
+    Query(ListStatusesParams { market_id }): Query<ListStatusesParams>,
+    State(AppState { db_conn, .. }): State<AppState>,
+) -> Result<Json<Vec<MarketStatus>>, (StatusCode, String)> {
+    let res = state::Query::find_statuses(&db_conn, market_id)
+        .await
+        .map_err(internal_error)?;
+
+    Ok(Json(res))
+}
@@ -4,16 +4,18 @@ use axum::{
     Json,
 };
 use serde::Deserialize;
-use sparker_core::{repo::trade, Trade};
+use sparker_core::{repo::trade, AmountFormat, Trade};
 use utoipa::IntoParams;
 
-use crate::{internal_error, AppState};
+use crate::{internal_error, market::find_format_market, AppState};
 
 #[derive(Deserialize, IntoParams)]
 pub struct ListTradesParams {
     market_id: String,
     limit: Option<u64>,
     offset: Option<u64>,
+    /// Adds the sizes and prices as decimal strings with `decimal`.
+    format: Option<AmountFormat>,
 }
 
 #[utoipa::path(
@@ -31,14 +33,24 @@ pub async fn list_trades(
         market_id,
         limit,
         offset,
+        format,
     }): Query<ListTradesParams>,
     State(AppState { db_conn, .. }): State<AppState>,
 ) -> Result<Json<Vec<Trade>>, (StatusCode, String)> {
     let limit = limit.unwrap_or(50);
     let offset = offset.unwrap_or(0);
+    let market = find_format_market(&db_conn, &market_id, format).await?;
     let res = trade::Query::find(&db_conn, market_id, limit, offset)
         .await
         .map_err(internal_error)?;
 
+    let res = match market {
+        Some(market) => res
+            .into_iter()
+            .map(|trade| trade.with_decimals(&market))
+            .collect(),
+        None => res,
+    };
+
     Ok(Json(res))
 }
@@ -0,0 +1,240 @@
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, DbErr as Error, EntityTrait, TransactionTrait,
+};
+use std::ops::Range;
+
+/// A row which was left out of a bulk insert.
+#[derive(Debug, Clone)]
+pub struct SkippedRow<T> {
+    /// Index of the row among the inserted ones.
+    pub index: usize,
+    pub row: T,
+    /// The constraint the row violates.
+    pub reason: String,
+}
+
+/// Inserts rows at once, leaving out the rows which violate a constraint.
+///
+/// The rows are inserted within a savepoint. If the insert fails on a unique or foreign key
+/// constraint, the batch is split in halves which are inserted the same way, until the
+/// offending rows are isolated. Any other error fails the whole insert, the halves inserted
+/// until then are left to be rolled back along with the transaction of the caller.
+///
+/// # Arguments
+///
+/// * `db_conn` - The connection (or transaction) to write with.
+/// * `models` - The rows to be inserted.
+/// * `on_conflict` - The conflict handling of the insert.
+///
+/// # Returns
+///
+/// Returns the indexes of the rows which were left out, along with the violated constraint.
+///
+pub async fn insert_isolating<C, A>(
+    db_conn: &C,
+    models: Vec<A>,
+    on_conflict: OnConflict,
+) -> Result<Vec<(usize, String)>, Error>
+where
+    C: TransactionTrait,
+    A: ActiveModelTrait + Send,
+{
+    let mut skipped = Vec::new();
+    let mut batches: Vec<Range<usize>> = Vec::new();
+    batches.push(0..models.len());
+
+    while let Some(batch) = batches.pop() {
+        if batch.is_empty() {
+            continue;
+        }
+
+        let savepoint = db_conn.begin().await?;
+        let res = A::Entity::insert_many(models[batch.clone()].to_vec())
+            .on_conflict(on_conflict.clone())
+            .do_nothing()
+            .exec(&savepoint)
+            .await;
+
+        match res {
+            Ok(_) => savepoint.commit().await?,
+            Err(e) => {
+                savepoint.rollback().await?;
+                let Some(violation) = e.sql_err() else {
+                    return Err(e);
+                };
+
+                if batch.len() == 1 {
+                    skipped.push((batch.start, violation.to_string()));
+                } else {
+                    // The first half is popped first, so the rows keep their order
+                    let middle = batch.start + batch.len() / 2;
+                    batches.push(middle..batch.end);
+                    batches.push(batch.start..middle);
+                }
+            }
+        }
+    }
+
+    Ok(skipped)
+}
+
+/// Pairs the indexes of skipped rows with the rows themselves.
+pub fn skipped_rows<T: Clone>(rows: &[T], skipped: Vec<(usize, String)>) -> Vec<SkippedRow<T>> {
+    skipped
+        .into_iter()
+        .map(|(index, reason)| SkippedRow {
+            index,
+            row: rows[index].clone(),
+            reason,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{
+        ActiveValue::Set, ConnectionTrait, Database, DatabaseConnection, DatabaseTransaction,
+        QueryOrder,
+    };
+
+    mod item {
+        use sea_orm::entity::prelude::*;
+
+        #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+        #[sea_orm(table_name = "item")]
+        pub struct Model {
+            #[sea_orm(primary_key, auto_increment = false)]
+            pub id: i32,
+            pub code: String,
+            pub amount: i32,
+        }
+
+        #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+        pub enum Relation {}
+
+        impl ActiveModelBehavior for ActiveModel {}
+    }
+
+    /// A database with an item whose code is `taken`, the code of an item is unique and its
+    /// amount can't be negative.
+    async fn database() -> DatabaseConnection {
+        let db_conn = Database::connect("sqlite::memory:").await.unwrap();
+        db_conn
+            .execute_unprepared(
+                "CREATE TABLE item (
+                    id INTEGER PRIMARY KEY,
+                    code TEXT NOT NULL UNIQUE,
+                    amount INTEGER NOT NULL CHECK (amount >= 0)
+                );
+                INSERT INTO item VALUES (0, 'taken', 0);",
+            )
+            .await
+            .unwrap();
+
+        db_conn
+    }
+
+    fn item(id: i32, code: &str, amount: i32) -> item::ActiveModel {
+        item::ActiveModel {
+            id: Set(id),
+            code: Set(code.to_string()),
+            amount: Set(amount),
+        }
+    }
+
+    async fn insert(
+        txn: &DatabaseTransaction,
+        items: Vec<item::ActiveModel>,
+    ) -> Result<Vec<(usize, String)>, Error> {
+        let on_conflict = OnConflict::column(item::Column::Id).do_nothing().to_owned();
+        insert_isolating(txn, items, on_conflict).await
+    }
+
+    async fn ids(txn: &DatabaseTransaction) -> Vec<i32> {
+        item::Entity::find()
+            .order_by_asc(item::Column::Id)
+            .all(txn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.id)
+            .collect()
+    }
+
+    fn indexes(skipped: &[(usize, String)]) -> Vec<usize> {
+        skipped.iter().map(|(index, _)| *index).collect()
+    }
+
+    #[tokio::test]
+    async fn isolates_a_bad_row_in_the_middle() {
+        let db_conn = database().await;
+        let txn = db_conn.begin().await.unwrap();
+
+        let items = (1..=5)
+            .map(|id| match id {
+                3 => item(id, "taken", 0),
+                _ => item(id, &format!("code-{}", id), 0),
+            })
+            .collect();
+        let skipped = insert(&txn, items).await.unwrap();
+
+        assert_eq!(indexes(&skipped), [2]);
+        assert!(skipped[0].1.contains("UNIQUE"), "{}", skipped[0].1);
+        assert_eq!(ids(&txn).await, [0, 1, 2, 4, 5]);
+    }
+
+    #[tokio::test]
+    async fn isolates_every_row_of_a_bad_batch() {
+        let db_conn = database().await;
+        let txn = db_conn.begin().await.unwrap();
+
+        let items = (1..=4).map(|id| item(id, "taken", 0)).collect();
+        let skipped = insert(&txn, items).await.unwrap();
+
+        // The rows are reported in their order
+        assert_eq!(indexes(&skipped), [0, 1, 2, 3]);
+        assert_eq!(ids(&txn).await, [0]);
+    }
+
+    #[tokio::test]
+    async fn inserts_a_batch_without_bad_rows_at_once() {
+        let db_conn = database().await;
+        let txn = db_conn.begin().await.unwrap();
+
+        // A conflict of the insert is handled by the insert itself
+        let items = vec![item(0, "other", 0), item(1, "code-1", 0)];
+        let skipped = insert(&txn, items).await.unwrap();
+
+        assert!(skipped.is_empty());
+        assert_eq!(ids(&txn).await, [0, 1]);
+    }
+
+    #[tokio::test]
+    async fn fails_on_an_error_which_is_not_a_constraint_violation() {
+        let db_conn = database().await;
+
+        // A check constraint can't be isolated, the insert fails as a whole
+        let txn = db_conn.begin().await.unwrap();
+        let items = vec![item(1, "code-1", 0), item(2, "code-2", -1)];
+        let e = insert(&txn, items).await.unwrap_err();
+
+        assert!(e.sql_err().is_none());
+        assert!(e.to_string().contains("CHECK"), "{}", e);
+        assert_eq!(ids(&txn).await, [0]);
+        txn.rollback().await.unwrap();
+
+        // Even once a bad row is isolated
+        let txn = db_conn.begin().await.unwrap();
+        let items = vec![
+            item(1, "code-1", 0),
+            item(2, "taken", 0),
+            item(3, "code-3", -1),
+        ];
+        let e = insert(&txn, items).await.unwrap_err();
+
+        assert!(e.to_string().contains("CHECK"), "{}", e);
+        txn.rollback().await.unwrap();
+        assert_eq!(item::Entity::find().all(&db_conn).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::Utc;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbErr as Error, EntityTrait, QueryFilter, QueryOrder,
+    Set,
+};
+use sparker_entity::dead_letter::{self, Entity as DeadLetterEntity};
+
+use crate::types::{DeadLetter, NewDeadLetter};
+
+pub struct Query;
+impl Query {
+    /// Finds the dead letters of a market which weren't redriven yet, oldest first.
+    pub async fn find_pending<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+    ) -> Result<Vec<DeadLetter>, Error> {
+        let dead_letters = DeadLetterEntity::find()
+            .filter(
+                Condition::all()
+                    .add(dead_letter::Column::MarketId.eq(market_id))
+                    .add(dead_letter::Column::RedrivenAt.is_null()),
+            )
+            .order_by_asc(dead_letter::Column::Id)
+            .all(db_conn)
+            .await?;
+        let dead_letters = dead_letters.into_iter().map(DeadLetter::from).collect();
+
+        Ok(dead_letters)
+    }
+}
+
+pub struct Mutation;
+impl Mutation {
+    pub async fn insert_many<C: ConnectionTrait>(
+        db_conn: &C,
+        data: Vec<NewDeadLetter>,
+    ) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let created_at = Utc::now().naive_utc();
+        let dead_letters = data
+            .into_iter()
+            .map(|dead_letter| dead_letter::ActiveModel {
+                market_id: Set(dead_letter.market_id),
+                block_number: Set(dead_letter.block_number.map(|block| block as i64)),
+                payload: Set(dead_letter.payload),
+                reason: Set(dead_letter.reason),
+                created_at: Set(created_at),
+                ..Default::default()
+            })
+            .collect::<Vec<dead_letter::ActiveModel>>();
+
+        DeadLetterEntity::insert_many(dead_letters)
+            .exec(db_conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_redriven<C: ConnectionTrait>(
+        db_conn: &C,
+        ids: Vec<i32>,
+    ) -> Result<u64, Error> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let res = DeadLetterEntity::update_many()
+            .set(dead_letter::ActiveModel {
+                redriven_at: Set(Some(Utc::now().naive_utc())),
+                ..Default::default()
+            })
+            .filter(dead_letter::Column::Id.is_in(ids))
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+
+    /// Deletes the pending dead letters starting from the given block, since their events
+    /// are going to be applied again.
+    ///
+    /// # Arguments
+    ///
+    /// * `market_id` - The market of the dead letters.
+    /// * `from_block` - The block number to start deleting from.
+    /// * `kept_reason` - The prefix of the reasons of the dead letters which are kept, because
+    ///   their events won't be seen again.
+    ///
+    pub async fn delete_many<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+        kept_reason: Option<&str>,
+    ) -> Result<u64, Error> {
+        let mut condition = Condition::all()
+            .add(dead_letter::Column::MarketId.eq(market_id))
+            .add(dead_letter::Column::BlockNumber.gte(from_block))
+            .add(dead_letter::Column::RedrivenAt.is_null());
+        if let Some(kept_reason) = kept_reason {
+            condition =
+                condition.add(dead_letter::Column::Reason.not_like(format!("{}%", kept_reason)));
+        }
+
+        let res = DeadLetterEntity::delete_many()
+            .filter(condition)
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::Utc;
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr as Error,
+    EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use sparker_entity::market::{self, Entity as MarketEntity};
+
+use crate::types::Market;
+
+pub struct Query;
+impl Query {
+    /// Finds the metadata of the markets, of a single market if given.
+    ///
+    /// Market ids are stored in lowercase, the given one matches in any case.
+    pub async fn find(
+        db_conn: &DatabaseConnection,
+        market_id: Option<String>,
+    ) -> Result<Vec<Market>, Error> {
+        let mut query = MarketEntity::find().order_by_asc(market::Column::Name);
+        if let Some(market_id) = market_id {
+            query = query.filter(market::Column::MarketId.eq(market_id.to_lowercase()));
+        }
+        let markets = query.all(db_conn).await?;
+        let markets = markets.into_iter().map(Market::from).collect();
+
+        Ok(markets)
+    }
+
+    /// Finds the metadata of a market, its id matches in any case.
+    pub async fn find_by_id<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+    ) -> Result<Option<Market>, Error> {
+        let market = MarketEntity::find()
+            .filter(market::Column::MarketId.eq(market_id.to_lowercase()))
+            .one(db_conn)
+            .await?;
+
+        Ok(market.map(Market::from))
+    }
+}
+
+pub struct Mutation;
+impl Mutation {
+    /// Stores the metadata of a market, replacing the previous one.
+    ///
+    /// The id of the market is stored in lowercase, so it's found whatever its case. The update
+    /// time of the market is set to now.
+    pub async fn upsert<C: ConnectionTrait>(db_conn: &C, data: Market) -> Result<(), Error> {
+        let market = market::ActiveModel {
+            market_id: Set(data.market_id.to_lowercase()),
+            name: Set(data.name),
+            chain: Set(data.chain),
+            base_asset: Set(data.base_asset.id),
+            base_symbol: Set(data.base_asset.symbol),
+            base_decimals: Set(data.base_asset.decimals as i32),
+            quote_asset: Set(data.quote_asset.id),
+            quote_symbol: Set(data.quote_asset.symbol),
+            quote_decimals: Set(data.quote_asset.decimals as i32),
+            price_precision: Set(data.price_precision as i32),
+            updated_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        };
+
+        let on_conflict = OnConflict::column(market::Column::MarketId)
+            .update_columns([
+                market::Column::Name,
+                market::Column::Chain,
+                market::Column::BaseAsset,
+                market::Column::BaseSymbol,
+                market::Column::BaseDecimals,
+                market::Column::QuoteAsset,
+                market::Column::QuoteSymbol,
+                market::Column::QuoteDecimals,
+                market::Column::PricePrecision,
+                market::Column::UpdatedAt,
+            ])
+            .to_owned();
+        MarketEntity::insert(market)
+            .on_conflict(on_conflict)
+            .exec(db_conn)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MarketAsset;
+    use sea_orm::{Database, Schema};
+
+    async fn database() -> DatabaseConnection {
+        let db_conn = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(db_conn.get_database_backend());
+        let statement = schema.create_table_from_entity(MarketEntity);
+        db_conn
+            .execute(db_conn.get_database_backend().build(&statement))
+            .await
+            .unwrap();
+
+        db_conn
+    }
+
+    fn market(market_id: &str, name: &str) -> Market {
+        let asset = |id: &str| MarketAsset {
+            id: id.to_string(),
+            symbol: None,
+            decimals: 9,
+        };
+
+        Market {
+            market_id: market_id.to_string(),
+            name: name.to_string(),
+            chain: "mainnet".to_string(),
+            base_asset: asset("0xbase"),
+            quote_asset: asset("0xquote"),
+            price_precision: 9,
+            updated_at: Utc::now().naive_utc(),
+        }
+    }
+
+    #[tokio::test]
+    async fn finds_a_market_whatever_the_case_of_its_id() {
+        let db_conn = database().await;
+        Mutation::upsert(&db_conn, market("0xABcd", "BTC-USDC"))
+            .await
+            .unwrap();
+
+        for market_id in ["0xabcd", "0xABCD", "0xABcd"] {
+            let found = Query::find_by_id(&db_conn, market_id).await.unwrap();
+            assert_eq!(found.unwrap().market_id, "0xabcd");
+
+            let found = Query::find(&db_conn, Some(market_id.to_string()))
+                .await
+                .unwrap();
+            assert_eq!(found.len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn replaces_a_market_registered_in_another_case() {
+        let db_conn = database().await;
+        Mutation::upsert(&db_conn, market("0xabcd", "BTC-USDC"))
+            .await
+            .unwrap();
+        Mutation::upsert(&db_conn, market("0xABCD", "BTC-USDT"))
+            .await
+            .unwrap();
+
+        let markets = Query::find(&db_conn, None).await.unwrap();
+        let names = markets
+            .iter()
+            .map(|market| market.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["BTC-USDT"]);
+    }
+}
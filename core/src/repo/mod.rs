@@ -1,3 +1,9 @@
+pub mod bulk;
+pub mod dead_letter;
+pub mod market;
 pub mod order;
+pub mod order_event;
+pub mod order_undo;
+pub mod raw_event;
 pub mod state;
 pub mod trade;
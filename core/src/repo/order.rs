@@ -1,13 +1,16 @@
 use sea_orm::{
-    sea_query::OnConflict, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait,
-    QueryFilter, QueryOrder, QuerySelect, Set,
+    sea_query::OnConflict, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use sparker_entity::{
     order::{self, Entity as OrderEntity},
     sea_orm_active_enums::{OrderStatus as OrderStatusSea, OrderType as OrderTypeSea},
 };
 
-use crate::types::{Order, OrderType, UpdateOrder};
+use crate::{
+    repo::bulk::{insert_isolating, skipped_rows, SkippedRow},
+    types::{Order, OrderType, UpdateOrder},
+};
 
 pub struct Query;
 impl Query {
@@ -22,7 +25,7 @@ impl Query {
             .one(db_conn)
             .await?;
 
-        let order = order.map(Order::from);
+        let order = order.map(Order::try_from).transpose()?;
 
         Ok(order)
     }
@@ -38,13 +41,13 @@ impl Query {
             .one(db_conn)
             .await?;
 
-        let order = order.map(Order::from);
+        let order = order.map(Order::try_from).transpose()?;
 
         Ok(order)
     }
 
-    pub async fn find_by_id(
-        db_conn: &DatabaseConnection,
+    pub async fn find_by_id<C: ConnectionTrait>(
+        db_conn: &C,
         order_id: &str,
     ) -> Result<Option<Order>, DbErr> {
         let order = OrderEntity::find()
@@ -52,11 +55,31 @@ impl Query {
             .one(db_conn)
             .await?;
 
-        let order = order.map(Order::from);
+        let order = order.map(Order::try_from).transpose()?;
 
         Ok(order)
     }
 
+    pub async fn find_by_ids<C: ConnectionTrait>(
+        db_conn: &C,
+        order_ids: Vec<String>,
+    ) -> Result<Vec<Order>, DbErr> {
+        if order_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let orders = OrderEntity::find()
+            .filter(order::Column::OrderId.is_in(order_ids))
+            .all(db_conn)
+            .await?;
+        let orders = orders
+            .into_iter()
+            .map(Order::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(orders)
+    }
+
     pub async fn find(
         db_conn: &DatabaseConnection,
         market_id: String,
@@ -74,7 +97,10 @@ impl Query {
             .limit(limit)
             .all(db_conn)
             .await?;
-        let orders = orders.into_iter().map(Order::from).collect();
+        let orders = orders
+            .into_iter()
+            .map(Order::try_from)
+            .collect::<Result<_, _>>()?;
 
         Ok(orders)
     }
@@ -92,7 +118,10 @@ impl Query {
             .limit(limit)
             .all(db_conn)
             .await?;
-        let orders = orders.into_iter().map(Order::from).collect();
+        let orders = orders
+            .into_iter()
+            .map(Order::try_from)
+            .collect::<Result<_, _>>()?;
 
         Ok(orders)
     }
@@ -118,7 +147,10 @@ impl Query {
         .limit(limit)
         .all(db_conn)
         .await?;
-        let orders = orders.into_iter().map(Order::from).collect();
+        let orders = orders
+            .into_iter()
+            .map(Order::try_from)
+            .collect::<Result<_, _>>()?;
 
         Ok(orders)
     }
@@ -126,15 +158,19 @@ impl Query {
 
 pub struct Mutation;
 impl Mutation {
-    pub async fn insert(db_conn: &DatabaseConnection, data: Order) -> Result<(), DbErr> {
+    pub async fn insert<C: ConnectionTrait>(db_conn: &C, data: Order) -> Result<(), DbErr> {
         let order = order::ActiveModel {
             tx_id: Set(data.tx_id),
             order_id: Set(data.order_id),
             order_type: Set(data.order_type.into()),
             user: Set(data.user),
             asset: Set(data.asset),
-            amount: Set(data.amount as i64),
-            price: Set(data.price as i64),
+            initial_amount: Set(data.initial_amount.into()),
+            filled_amount: Set(data.filled_amount.into()),
+            remaining_amount: Set(data.remaining_amount.into()),
+            price: Set(data.price.into()),
+            average_fill_price: Set(data.average_fill_price.into()),
+            fill_volume: Set(data.fill_volume.into()),
             status: Set(data.status.into()),
             block_number: Set(data.block_number as i64),
             timestamp: Set(data.timestamp),
@@ -153,35 +189,21 @@ impl Mutation {
         Ok(())
     }
 
-    pub async fn insert_many(db_conn: &DatabaseConnection, data: Vec<Order>) -> Result<(), DbErr> {
+    pub async fn insert_many<C: ConnectionTrait>(
+        db_conn: &C,
+        data: Vec<Order>,
+    ) -> Result<(), DbErr> {
         if data.is_empty() {
             return Ok(());
         }
 
         let orders = data
             .into_iter()
-            .map(|order| order::ActiveModel {
-                tx_id: Set(order.tx_id),
-                order_id: Set(order.order_id),
-                order_type: Set(order.order_type.into()),
-                user: Set(order.user),
-                asset: Set(order.asset),
-                amount: Set(order.amount as i64),
-                price: Set(order.price as i64),
-                status: Set(order.status.into()),
-                block_number: Set(order.block_number as i64),
-                timestamp: Set(order.timestamp),
-                market_id: Set(order.market_id),
-                ..Default::default()
-            })
+            .map(build_model)
             .collect::<Vec<order::ActiveModel>>();
 
-        let on_conflict = OnConflict::column(order::Column::OrderId)
-            .do_nothing()
-            .to_owned();
-
         OrderEntity::insert_many(orders)
-            .on_conflict(on_conflict)
+            .on_conflict(open_on_conflict())
             .do_nothing()
             .exec(db_conn)
             .await?;
@@ -189,7 +211,34 @@ impl Mutation {
         Ok(())
     }
 
-    pub async fn update(db_conn: &DatabaseConnection, data: UpdateOrder) -> Result<Order, DbErr> {
+    /// Inserts orders at once like [`Mutation::insert_many`], leaving out the orders which
+    /// violate a constraint.
+    ///
+    /// # Returns
+    ///
+    /// Returns the orders which were left out along with the reason.
+    pub async fn insert_many_isolating<C: TransactionTrait>(
+        db_conn: &C,
+        data: Vec<Order>,
+    ) -> Result<Vec<SkippedRow<Order>>, DbErr> {
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let orders = data
+            .iter()
+            .cloned()
+            .map(build_model)
+            .collect::<Vec<order::ActiveModel>>();
+        let skipped = insert_isolating(db_conn, orders, open_on_conflict()).await?;
+
+        Ok(skipped_rows(&data, skipped))
+    }
+
+    pub async fn update<C: ConnectionTrait>(
+        db_conn: &C,
+        data: UpdateOrder,
+    ) -> Result<Order, DbErr> {
         let order = OrderEntity::find()
             .filter(order::Column::OrderId.eq(&data.order_id))
             .one(db_conn)
@@ -201,18 +250,88 @@ impl Mutation {
             )))?
             .into();
 
-        if let Some(amount) = data.amount {
-            order.amount = Set(amount as i64);
+        if let Some(filled_amount) = data.filled_amount {
+            order.filled_amount = Set(filled_amount.into());
+        }
+        if let Some(remaining_amount) = data.remaining_amount {
+            order.remaining_amount = Set(remaining_amount.into());
+        }
+        if let Some(average_fill_price) = data.average_fill_price {
+            order.average_fill_price = Set(average_fill_price.into());
+        }
+        if let Some(fill_volume) = data.fill_volume {
+            order.fill_volume = Set(fill_volume.into());
         }
         order.status = Set(data.status.into());
 
         let order = OrderEntity::update(order).exec(db_conn).await?;
 
-        Ok(Order::from(order))
+        Ok(Order::try_from(order)?)
     }
 
-    pub async fn delete_many(
-        db_conn: &DatabaseConnection,
+    /// Writes the amounts and status of orders which were filled in memory at once.
+    ///
+    /// The orders are expected to exist, their other columns are left untouched.
+    pub async fn update_fills<C: ConnectionTrait>(
+        db_conn: &C,
+        data: Vec<Order>,
+    ) -> Result<(), DbErr> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let orders = data
+            .into_iter()
+            .map(build_model)
+            .collect::<Vec<order::ActiveModel>>();
+
+        let on_conflict = OnConflict::column(order::Column::OrderId)
+            .update_columns([
+                order::Column::FilledAmount,
+                order::Column::RemainingAmount,
+                order::Column::AverageFillPrice,
+                order::Column::FillVolume,
+                order::Column::Status,
+            ])
+            .to_owned();
+        OrderEntity::insert_many(orders)
+            .on_conflict(on_conflict)
+            .exec(db_conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Overwrites an order with a previously recorded state.
+    pub async fn restore<C: ConnectionTrait>(db_conn: &C, data: Order) -> Result<(), DbErr> {
+        let order = order::ActiveModel {
+            tx_id: Set(data.tx_id),
+            order_type: Set(data.order_type.into()),
+            user: Set(data.user),
+            asset: Set(data.asset),
+            initial_amount: Set(data.initial_amount.into()),
+            filled_amount: Set(data.filled_amount.into()),
+            remaining_amount: Set(data.remaining_amount.into()),
+            price: Set(data.price.into()),
+            average_fill_price: Set(data.average_fill_price.into()),
+            fill_volume: Set(data.fill_volume.into()),
+            status: Set(data.status.into()),
+            block_number: Set(data.block_number as i64),
+            timestamp: Set(data.timestamp),
+            market_id: Set(data.market_id),
+            ..Default::default()
+        };
+        OrderEntity::update_many()
+            .set(order)
+            .filter(order::Column::OrderId.eq(data.order_id))
+            .exec(db_conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_many<C: ConnectionTrait>(
+        db_conn: &C,
         market_id: String,
         from_block: i64,
     ) -> Result<u64, DbErr> {
@@ -229,6 +348,49 @@ impl Mutation {
     }
 }
 
+fn build_model(order: Order) -> order::ActiveModel {
+    order::ActiveModel {
+        tx_id: Set(order.tx_id),
+        order_id: Set(order.order_id),
+        order_type: Set(order.order_type.into()),
+        user: Set(order.user),
+        asset: Set(order.asset),
+        initial_amount: Set(order.initial_amount.into()),
+        filled_amount: Set(order.filled_amount.into()),
+        remaining_amount: Set(order.remaining_amount.into()),
+        price: Set(order.price.into()),
+        average_fill_price: Set(order.average_fill_price.into()),
+        fill_volume: Set(order.fill_volume.into()),
+        status: Set(order.status.into()),
+        block_number: Set(order.block_number as i64),
+        timestamp: Set(order.timestamp),
+        market_id: Set(order.market_id),
+        ..Default::default()
+    }
+}
+
+/// Conflict handling of opened orders: orders are immutable except for the re-opening of
+/// cancelled ones.
+fn open_on_conflict() -> OnConflict {
+    OnConflict::column(order::Column::OrderId)
+        .update_columns([
+            order::Column::TxId,
+            order::Column::User,
+            order::Column::Asset,
+            order::Column::InitialAmount,
+            order::Column::FilledAmount,
+            order::Column::RemainingAmount,
+            order::Column::Price,
+            order::Column::AverageFillPrice,
+            order::Column::FillVolume,
+            order::Column::Status,
+            order::Column::BlockNumber,
+            order::Column::Timestamp,
+        ])
+        .action_and_where(order::Column::Status.eq(OrderStatusSea::Cancelled))
+        .to_owned()
+}
+
 fn is_active_condition() -> Condition {
     Condition::any()
         .add(order::Column::Status.eq(OrderStatusSea::New))
@@ -0,0 +1,102 @@
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr as Error, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use sparker_entity::order_event::{self, Entity as OrderEventEntity};
+
+use crate::types::OrderEvent;
+
+pub struct Query;
+impl Query {
+    /// Finds the transitions of an order in the order they happened.
+    pub async fn find_by_order_id(
+        db_conn: &DatabaseConnection,
+        order_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<OrderEvent>, Error> {
+        let events = OrderEventEntity::find()
+            .filter(order_event::Column::OrderId.eq(order_id))
+            .order_by_asc(order_event::Column::Id)
+            .offset(offset)
+            .limit(limit)
+            .all(db_conn)
+            .await?;
+        let events = events
+            .into_iter()
+            .map(OrderEvent::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(events)
+    }
+
+    pub async fn find(
+        db_conn: &DatabaseConnection,
+        market_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<OrderEvent>, Error> {
+        let events = OrderEventEntity::find()
+            .filter(order_event::Column::MarketId.eq(market_id))
+            .order_by_desc(order_event::Column::Id)
+            .offset(offset)
+            .limit(limit)
+            .all(db_conn)
+            .await?;
+        let events = events
+            .into_iter()
+            .map(OrderEvent::try_from)
+            .collect::<Result<_, _>>()?;
+
+        Ok(events)
+    }
+}
+
+pub struct Mutation;
+impl Mutation {
+    pub async fn insert_many<C: ConnectionTrait>(
+        db_conn: &C,
+        data: Vec<OrderEvent>,
+    ) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let events = data
+            .into_iter()
+            .map(|event| order_event::ActiveModel {
+                order_id: Set(event.order_id),
+                event_type: Set(event.event_type.into()),
+                tx_id: Set(event.tx_id),
+                block_number: Set(event.block_number as i64),
+                timestamp: Set(event.timestamp),
+                amount_before: Set(event.amount_before.into()),
+                amount_after: Set(event.amount_after.into()),
+                status: Set(event.status.into()),
+                market_id: Set(event.market_id),
+                ..Default::default()
+            })
+            .collect::<Vec<order_event::ActiveModel>>();
+
+        OrderEventEntity::insert_many(events).exec(db_conn).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_many<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: String,
+        from_block: i64,
+    ) -> Result<u64, Error> {
+        let res = OrderEventEntity::delete_many()
+            .filter(
+                Condition::all()
+                    .add(order_event::Column::MarketId.eq(market_id))
+                    .add(order_event::Column::BlockNumber.gte(from_block)),
+            )
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+}
@@ -0,0 +1,142 @@
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbErr as Error, EntityTrait, QueryFilter, QueryOrder,
+    Set,
+};
+use sparker_entity::order_undo::{self, Entity as OrderUndoEntity};
+use std::collections::HashSet;
+
+use crate::{repo, types::Order};
+
+pub struct Query;
+impl Query {
+    /// Finds the state each order had before the given block, for the orders which were
+    /// changed at or after the block.
+    pub async fn find_states_before<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+    ) -> Result<Vec<Order>, Error> {
+        let undos = OrderUndoEntity::find()
+            .filter(
+                Condition::all()
+                    .add(order_undo::Column::MarketId.eq(market_id))
+                    .add(order_undo::Column::BlockNumber.gte(from_block)),
+            )
+            .order_by_asc(order_undo::Column::Id)
+            .all(db_conn)
+            .await?;
+
+        // The earliest record of an order holds its state before the first change
+        let mut seen = HashSet::new();
+        let mut states = Vec::new();
+        for undo in undos {
+            if seen.insert(undo.order_id) {
+                let order = serde_json::from_value::<Order>(undo.state)
+                    .map_err(|e| Error::Json(e.to_string()))?;
+                states.push(order);
+            }
+        }
+
+        Ok(states)
+    }
+}
+
+pub struct Mutation;
+impl Mutation {
+    /// Records the states of orders right before they are changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `market_id` - The market of the orders.
+    /// * `data` - The block number of the change and the previous state of the order.
+    ///
+    pub async fn insert_many<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        data: Vec<(i64, Order)>,
+    ) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let undos = data
+            .into_iter()
+            .map(|(block_number, order)| {
+                let state = serde_json::to_value(&order).map_err(|e| Error::Json(e.to_string()))?;
+
+                Ok(order_undo::ActiveModel {
+                    market_id: Set(market_id.to_owned()),
+                    order_id: Set(order.order_id),
+                    block_number: Set(block_number),
+                    state: Set(state),
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<Vec<order_undo::ActiveModel>, Error>>()?;
+
+        OrderUndoEntity::insert_many(undos).exec(db_conn).await?;
+
+        Ok(())
+    }
+
+    /// Reverts the orders changed at or after the given block to their previous states and
+    /// drops the records.
+    ///
+    /// Orders opened at or after the block are restored to their opening state, deleting
+    /// them is left to the caller.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of reverted orders.
+    ///
+    pub async fn revert<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+    ) -> Result<u64, Error> {
+        let states = Query::find_states_before(db_conn, market_id, from_block).await?;
+        let reverted = states.len() as u64;
+
+        for order in states {
+            repo::order::Mutation::restore(db_conn, order).await?;
+        }
+        Self::delete_many(db_conn, market_id, from_block).await?;
+
+        Ok(reverted)
+    }
+
+    pub async fn delete_many<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+    ) -> Result<u64, Error> {
+        let res = OrderUndoEntity::delete_many()
+            .filter(
+                Condition::all()
+                    .add(order_undo::Column::MarketId.eq(market_id))
+                    .add(order_undo::Column::BlockNumber.gte(from_block)),
+            )
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+
+    /// Drops the records which are too old to be reverted.
+    pub async fn delete_before<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        block: i64,
+    ) -> Result<u64, Error> {
+        let res = OrderUndoEntity::delete_many()
+            .filter(
+                Condition::all()
+                    .add(order_undo::Column::MarketId.eq(market_id))
+                    .add(order_undo::Column::BlockNumber.lt(block)),
+            )
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+}
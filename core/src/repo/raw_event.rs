@@ -0,0 +1,116 @@
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, Condition, ConnectionTrait, DbErr as Error, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use sparker_entity::raw_event::{self, Entity as RawEventEntity};
+
+use crate::types::RawEvent;
+
+pub struct Query;
+impl Query {
+    /// Finds the journaled events of a market in the `[from_block, to_block]` range, in chain
+    /// order.
+    pub async fn find_range<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+        to_block: i64,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<RawEvent>, Error> {
+        let raw_events = RawEventEntity::find()
+            .filter(
+                Condition::all()
+                    .add(raw_event::Column::MarketId.eq(market_id))
+                    .add(raw_event::Column::BlockNumber.gte(from_block))
+                    .add(raw_event::Column::BlockNumber.lte(to_block)),
+            )
+            .order_by_asc(raw_event::Column::BlockNumber)
+            .order_by_asc(raw_event::Column::TransactionIndex)
+            .order_by_asc(raw_event::Column::LogIndex)
+            .offset(offset)
+            .limit(limit)
+            .all(db_conn)
+            .await?;
+        let raw_events = raw_events.into_iter().map(RawEvent::from).collect();
+
+        Ok(raw_events)
+    }
+
+    pub async fn find_latest_block<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+    ) -> Result<Option<i64>, Error> {
+        let block_number = RawEventEntity::find()
+            .select_only()
+            .column(raw_event::Column::BlockNumber)
+            .filter(raw_event::Column::MarketId.eq(market_id))
+            .order_by_desc(raw_event::Column::BlockNumber)
+            .into_tuple::<i64>()
+            .one(db_conn)
+            .await?;
+
+        Ok(block_number)
+    }
+}
+
+pub struct Mutation;
+impl Mutation {
+    /// Appends events to the journal, the events which are already there are skipped.
+    pub async fn insert_many<C: ConnectionTrait>(
+        db_conn: &C,
+        data: Vec<RawEvent>,
+    ) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let raw_events = data
+            .into_iter()
+            .map(|raw_event| raw_event::ActiveModel {
+                market_id: Set(raw_event.market_id),
+                block_number: Set(raw_event.block_number as i64),
+                block_hash: Set(raw_event.block_hash),
+                transaction_hash: Set(raw_event.transaction_hash),
+                transaction_index: Set(raw_event.transaction_index as i64),
+                log_index: Set(raw_event.log_index as i64),
+                payload: Set(raw_event.payload),
+                ..Default::default()
+            })
+            .collect::<Vec<raw_event::ActiveModel>>();
+
+        let on_conflict = OnConflict::columns([
+            raw_event::Column::TransactionHash,
+            raw_event::Column::LogIndex,
+        ])
+        .do_nothing()
+        .to_owned();
+
+        RawEventEntity::insert_many(raw_events)
+            .on_conflict(on_conflict)
+            .do_nothing()
+            .exec(db_conn)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Deletes the journaled events starting from the given block, i.e. the events of blocks
+    /// which are no longer on the canonical chain.
+    pub async fn delete_many<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+    ) -> Result<u64, Error> {
+        let res = RawEventEntity::delete_many()
+            .filter(
+                Condition::all()
+                    .add(raw_event::Column::MarketId.eq(market_id))
+                    .add(raw_event::Column::BlockNumber.gte(from_block)),
+            )
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
+}
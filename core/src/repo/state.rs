@@ -1,9 +1,14 @@
 use chrono::Utc;
 use sea_orm::{
-    sea_query::OnConflict, ColumnTrait, DatabaseConnection, DbErr as Error, EntityTrait,
-    QueryFilter, Set,
+    sea_query::OnConflict, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DbErr as Error, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
 };
-use sparker_entity::state::{self, Entity as StateEntity};
+use sparker_entity::{
+    block::{self, Entity as BlockEntity},
+    state::{self, Entity as StateEntity},
+};
+
+use crate::types::{IndexerStatus, MarketStatus};
 
 pub struct Query;
 impl Query {
@@ -18,12 +23,48 @@ impl Query {
 
         Ok(state.map(|state| state.latest_processed_block))
     }
+
+    /// Finds the status of the indexers, of a single market if given.
+    pub async fn find_statuses(
+        db_conn: &DatabaseConnection,
+        market_id: Option<String>,
+    ) -> Result<Vec<MarketStatus>, Error> {
+        let mut query = StateEntity::find().order_by_asc(state::Column::MarketId);
+        if let Some(market_id) = market_id {
+            query = query.filter(state::Column::MarketId.eq(market_id));
+        }
+        let statuses = query.all(db_conn).await?;
+        let statuses = statuses.into_iter().map(MarketStatus::from).collect();
+
+        Ok(statuses)
+    }
+
+    pub async fn find_block_hash<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        block_number: i64,
+    ) -> Result<Option<String>, Error> {
+        let block_hash = BlockEntity::find()
+            .select_only()
+            .column(block::Column::BlockHash)
+            .filter(
+                Condition::all()
+                    .add(block::Column::MarketId.eq(market_id))
+                    .add(block::Column::BlockNumber.eq(block_number)),
+            )
+            .into_tuple::<String>()
+            .one(db_conn)
+            .await?;
+
+        Ok(block_hash)
+    }
 }
 
 pub struct Mutation;
 impl Mutation {
-    pub async fn upsert_latest_processed_block(
-        db_conn: &DatabaseConnection,
+    /// Stores the latest processed block of a market along with the time it's written.
+    pub async fn upsert_latest_processed_block<C: ConnectionTrait>(
+        db_conn: &C,
         block: i64,
         market_id: &str,
     ) -> Result<(), Error> {
@@ -35,7 +76,10 @@ impl Mutation {
         };
 
         let on_conflict = OnConflict::column(state::Column::MarketId)
-            .update_column(state::Column::LatestProcessedBlock)
+            .update_columns([
+                state::Column::LatestProcessedBlock,
+                state::Column::Timestamp,
+            ])
             .to_owned();
         StateEntity::insert(state)
             .on_conflict(on_conflict)
@@ -44,4 +88,82 @@ impl Mutation {
 
         Ok(())
     }
+
+    /// Stores the status reported by the indexer of a market.
+    ///
+    /// The status is stored along with the latest processed block, so nothing is stored until
+    /// the market has processed its first block.
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the market has a state to store the status in.
+    pub async fn update_status<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        status: &IndexerStatus,
+    ) -> Result<bool, Error> {
+        let res = StateEntity::update_many()
+            .set(state::ActiveModel {
+                chain_head: Set(status.chain_head.map(|block| block as i64)),
+                last_event_at: Set(status.last_event_at),
+                mode: Set(status.mode.into()),
+                last_error: Set(status.last_error.clone()),
+                reconnect_count: Set(status.reconnect_count as i32),
+                status_updated_at: Set(Some(Utc::now().naive_utc())),
+                ..Default::default()
+            })
+            .filter(state::Column::MarketId.eq(market_id))
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected > 0)
+    }
+
+    pub async fn upsert_block_hashes<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        blocks: Vec<(i64, String)>,
+    ) -> Result<(), Error> {
+        if blocks.is_empty() {
+            return Ok(());
+        }
+
+        let blocks = blocks
+            .into_iter()
+            .map(|(block_number, block_hash)| block::ActiveModel {
+                market_id: Set(market_id.to_owned()),
+                block_number: Set(block_number),
+                block_hash: Set(block_hash),
+                ..Default::default()
+            })
+            .collect::<Vec<block::ActiveModel>>();
+
+        let on_conflict =
+            OnConflict::columns([block::Column::MarketId, block::Column::BlockNumber])
+                .update_column(block::Column::BlockHash)
+                .to_owned();
+        BlockEntity::insert_many(blocks)
+            .on_conflict(on_conflict)
+            .exec(db_conn)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_block_hashes<C: ConnectionTrait>(
+        db_conn: &C,
+        market_id: &str,
+        from_block: i64,
+    ) -> Result<u64, Error> {
+        let res = BlockEntity::delete_many()
+            .filter(
+                Condition::all()
+                    .add(block::Column::MarketId.eq(market_id))
+                    .add(block::Column::BlockNumber.gte(from_block)),
+            )
+            .exec(db_conn)
+            .await?;
+
+        Ok(res.rows_affected)
+    }
 }
@@ -1,10 +1,13 @@
 use sea_orm::{
-    sea_query::OnConflict, ColumnTrait, Condition, DatabaseConnection, DbErr as Error, EntityTrait,
-    QueryFilter, QueryOrder, QuerySelect, Set,
+    sea_query::OnConflict, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DbErr as Error, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
 };
 use sparker_entity::trade::{self, Entity as TradeEntity};
 
-use crate::types::Trade;
+use crate::{
+    repo::bulk::{insert_isolating, skipped_rows, SkippedRow},
+    types::Trade,
+};
 
 pub struct Query;
 impl Query {
@@ -21,7 +24,10 @@ impl Query {
             .limit(limit)
             .all(db_conn)
             .await?;
-        let trades = trades.into_iter().map(Trade::from).collect();
+        let trades = trades
+            .into_iter()
+            .map(Trade::try_from)
+            .collect::<Result<_, _>>()?;
 
         Ok(trades)
     }
@@ -29,15 +35,15 @@ impl Query {
 
 pub struct Mutation;
 impl Mutation {
-    pub async fn insert(db_conn: &DatabaseConnection, data: Trade) -> Result<(), Error> {
+    pub async fn insert<C: ConnectionTrait>(db_conn: &C, data: Trade) -> Result<(), Error> {
         let trade = trade::ActiveModel {
             tx_id: Set(data.tx_id),
             trade_id: Set(data.trade_id),
             order_id: Set(data.order_id),
             limit_type: Set(data.limit_type.into()),
             user: Set(data.user),
-            size: Set(data.size as i64),
-            price: Set(data.price as i64),
+            size: Set(data.size.into()),
+            price: Set(data.price.into()),
             timestamp: Set(data.timestamp),
             market_id: Set(data.market_id),
             block_number: Set(data.block_number as i64),
@@ -55,26 +61,17 @@ impl Mutation {
         Ok(())
     }
 
-    pub async fn insert_many(db_conn: &DatabaseConnection, data: Vec<Trade>) -> Result<(), Error> {
+    pub async fn insert_many<C: ConnectionTrait>(
+        db_conn: &C,
+        data: Vec<Trade>,
+    ) -> Result<(), Error> {
         if data.is_empty() {
             return Ok(());
         }
 
         let trades = data
             .into_iter()
-            .map(|trade| trade::ActiveModel {
-                tx_id: Set(trade.tx_id),
-                trade_id: Set(trade.trade_id),
-                order_id: Set(trade.order_id),
-                limit_type: Set(trade.limit_type.into()),
-                user: Set(trade.user),
-                size: Set(trade.size as i64),
-                price: Set(trade.price as i64),
-                timestamp: Set(trade.timestamp),
-                market_id: Set(trade.market_id),
-                block_number: Set(trade.block_number as i64),
-                ..Default::default()
-            })
+            .map(build_model)
             .collect::<Vec<trade::ActiveModel>>();
 
         let on_conflict = OnConflict::column(trade::Column::TradeId)
@@ -90,8 +87,36 @@ impl Mutation {
         Ok(())
     }
 
-    pub async fn delete_many(
-        db_conn: &DatabaseConnection,
+    /// Inserts trades at once, leaving out the trades which violate a constraint, e.g. the
+    /// trades of a missing order.
+    ///
+    /// # Returns
+    ///
+    /// Returns the trades which were left out along with the reason.
+    pub async fn insert_many_isolating<C: TransactionTrait>(
+        db_conn: &C,
+        data: Vec<Trade>,
+    ) -> Result<Vec<SkippedRow<Trade>>, Error> {
+        if data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let trades = data
+            .iter()
+            .cloned()
+            .map(build_model)
+            .collect::<Vec<trade::ActiveModel>>();
+
+        let on_conflict = OnConflict::column(trade::Column::TradeId)
+            .do_nothing()
+            .to_owned();
+        let skipped = insert_isolating(db_conn, trades, on_conflict).await?;
+
+        Ok(skipped_rows(&data, skipped))
+    }
+
+    pub async fn delete_many<C: ConnectionTrait>(
+        db_conn: &C,
         market_id: String,
         from_block: i64,
     ) -> Result<u64, Error> {
@@ -107,3 +132,19 @@ impl Mutation {
         Ok(res.rows_affected)
     }
 }
+
+fn build_model(trade: Trade) -> trade::ActiveModel {
+    trade::ActiveModel {
+        tx_id: Set(trade.tx_id),
+        trade_id: Set(trade.trade_id),
+        order_id: Set(trade.order_id),
+        limit_type: Set(trade.limit_type.into()),
+        user: Set(trade.user),
+        size: Set(trade.size.into()),
+        price: Set(trade.price.into()),
+        timestamp: Set(trade.timestamp),
+        market_id: Set(trade.market_id),
+        block_number: Set(trade.block_number as i64),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,43 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// A raw event which couldn't be applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub id: i32,
+    pub market_id: String,
+    /// Block of the event, unknown if the event couldn't be decoded.
+    pub block_number: Option<u64>,
+    pub payload: String,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+    /// When the event was applied again, if ever.
+    pub redriven_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewDeadLetter {
+    pub market_id: String,
+    pub block_number: Option<u64>,
+    pub payload: String,
+    pub reason: String,
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::dead_letter;
+    impl From<dead_letter::Model> for DeadLetter {
+        fn from(dead_letter: dead_letter::Model) -> Self {
+            Self {
+                id: dead_letter.id,
+                market_id: dead_letter.market_id,
+                block_number: dead_letter.block_number.map(|block| block as u64),
+                payload: dead_letter.payload,
+                reason: dead_letter.reason,
+                created_at: dead_letter.created_at,
+                redriven_at: dead_letter.redriven_at,
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+/// How the amounts and prices are returned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum AmountFormat {
+    /// Raw integers only.
+    #[default]
+    Raw,
+    /// Decimal strings scaled by the decimals of the market, along with the raw integers.
+    Decimal,
+}
+
+#[cfg(feature = "with-proto")]
+mod with_proto {
+    use super::*;
+    use sparker_proto::types as proto;
+
+    impl From<proto::AmountFormat> for AmountFormat {
+        fn from(format: proto::AmountFormat) -> Self {
+            match format {
+                proto::AmountFormat::Raw => AmountFormat::Raw,
+                proto::AmountFormat::Decimal => AmountFormat::Decimal,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Market, MarketAsset, U128};
+    use chrono::DateTime;
+
+    /// A market of the given decimals of the base asset and of the prices.
+    fn market(base_decimals: u32, price_precision: u32) -> Market {
+        let asset = |id: &str, decimals| MarketAsset {
+            id: id.to_string(),
+            symbol: None,
+            decimals,
+        };
+
+        Market {
+            market_id: "0xmarket".to_string(),
+            name: "ETH-USDC".to_string(),
+            chain: "mainnet".to_string(),
+            base_asset: asset("0xbase", base_decimals),
+            quote_asset: asset("0xquote", 6),
+            price_precision,
+            updated_at: DateTime::from_timestamp(0, 0).unwrap().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn drops_the_trailing_zeros_of_the_fraction() {
+        let market = market(9, 9);
+
+        assert_eq!(market.format_amount(U128(1_500_000_000)), "1.5");
+        assert_eq!(market.format_amount(U128(2_000_000_000)), "2");
+        assert_eq!(market.format_amount(U128(10_000_000_000)), "10");
+        assert_eq!(market.format_price(U128(1_000_000_010)), "1.00000001");
+        assert_eq!(market.format_amount(U128::ZERO), "0");
+    }
+
+    #[test]
+    fn formats_the_values_below_one() {
+        let market = market(9, 9);
+
+        assert_eq!(market.format_amount(U128(1)), "0.000000001");
+        assert_eq!(market.format_amount(U128(120)), "0.00000012");
+        assert_eq!(market.format_price(U128(999_999_999)), "0.999999999");
+    }
+
+    #[test]
+    fn formats_the_raw_value_without_decimals() {
+        let market = market(0, 0);
+
+        assert_eq!(market.format_amount(U128(0)), "0");
+        assert_eq!(market.format_amount(U128(1)), "1");
+        assert_eq!(market.format_price(U128(1_000)), "1000");
+    }
+
+    #[test]
+    fn formats_the_very_large_values_exactly() {
+        let market = market(18, 9);
+
+        // Far beyond the precision of a float
+        assert_eq!(
+            market.format_amount(U128(u128::MAX)),
+            "340282366920938463463.374607431768211455"
+        );
+        assert_eq!(
+            market.format_price(U128(123_456_789_012_345_678_901_234_567_890)),
+            "123456789012345678901.23456789"
+        );
+        assert_eq!(
+            market.format_amount(U128(10u128.pow(38))),
+            "100000000000000000000"
+        );
+    }
+
+    #[test]
+    fn names_the_formats_in_lowercase() {
+        assert_eq!(AmountFormat::default(), AmountFormat::Raw);
+        assert_eq!(
+            serde_json::to_string(&AmountFormat::Decimal).unwrap(),
+            "\"decimal\""
+        );
+        assert_eq!(
+            serde_json::from_str::<AmountFormat>("\"raw\"").unwrap(),
+            AmountFormat::Raw
+        );
+    }
+}
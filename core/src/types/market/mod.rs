@@ -0,0 +1,104 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::U128;
+
+mod format;
+
+pub use format::*;
+
+/// Metadata of a market, which tells how its amounts and prices are scaled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub struct Market {
+    pub market_id: String,
+    pub name: String,
+    /// Network the market is deployed on.
+    pub chain: String,
+    /// Asset the amounts of the orders are in.
+    pub base_asset: MarketAsset,
+    /// Asset the prices of the orders are in.
+    pub quote_asset: MarketAsset,
+    /// Number of decimals of the prices.
+    pub price_precision: u32,
+    pub updated_at: NaiveDateTime,
+}
+
+/// An asset of a market.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub struct MarketAsset {
+    pub id: String,
+    pub symbol: Option<String>,
+    /// Number of decimals of the amounts of the asset.
+    pub decimals: u32,
+}
+
+impl Market {
+    /// Formats an amount of the base asset as a decimal string.
+    pub fn format_amount(&self, amount: U128) -> String {
+        amount.to_decimal(self.base_asset.decimals)
+    }
+
+    /// Formats a price as a decimal string.
+    pub fn format_price(&self, price: U128) -> String {
+        price.to_decimal(self.price_precision)
+    }
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::market;
+    impl From<market::Model> for Market {
+        fn from(market: market::Model) -> Self {
+            Self {
+                market_id: market.market_id,
+                name: market.name,
+                chain: market.chain,
+                base_asset: MarketAsset {
+                    id: market.base_asset,
+                    symbol: market.base_symbol,
+                    decimals: market.base_decimals as u32,
+                },
+                quote_asset: MarketAsset {
+                    id: market.quote_asset,
+                    symbol: market.quote_symbol,
+                    decimals: market.quote_decimals as u32,
+                },
+                price_precision: market.price_precision as u32,
+                updated_at: market.updated_at,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "with-proto")]
+mod with_proto {
+    use super::*;
+    use sparker_proto::types as proto;
+
+    impl From<Market> for proto::Market {
+        fn from(market: Market) -> Self {
+            Self {
+                market_id: market.market_id,
+                name: market.name,
+                chain: market.chain,
+                base_asset: Some(market.base_asset.into()),
+                quote_asset: Some(market.quote_asset.into()),
+                price_precision: market.price_precision,
+                updated_at: market.updated_at.and_utc().timestamp() as u64,
+            }
+        }
+    }
+
+    impl From<MarketAsset> for proto::MarketAsset {
+        fn from(asset: MarketAsset) -> Self {
+            Self {
+                id: asset.id,
+                symbol: asset.symbol,
+                decimals: asset.decimals,
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+mod mode;
+
+pub use mode::*;
+
+/// Progress of the indexer of a market, which tells how fresh the market data is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub struct MarketStatus {
+    pub market_id: String,
+    pub latest_processed_block: u64,
+    /// Latest block of the chain seen by the indexer.
+    pub chain_head: Option<u64>,
+    /// Number of blocks the market is behind the chain head.
+    pub lag_blocks: Option<u64>,
+    /// Block time of the latest event of the market.
+    pub last_event_at: Option<NaiveDateTime>,
+    pub mode: IndexerMode,
+    pub last_error: Option<String>,
+    /// Number of times the indexer reconnected to its source since it started.
+    pub reconnect_count: u32,
+    /// When the indexer last reported its status.
+    pub updated_at: NaiveDateTime,
+}
+
+/// Status reported by the indexer of a market.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexerStatus {
+    pub chain_head: Option<u64>,
+    pub last_event_at: Option<NaiveDateTime>,
+    pub mode: IndexerMode,
+    pub last_error: Option<String>,
+    pub reconnect_count: u32,
+}
+
+impl Default for IndexerStatus {
+    fn default() -> Self {
+        Self {
+            chain_head: None,
+            last_event_at: None,
+            mode: IndexerMode::CatchingUp,
+            last_error: None,
+            reconnect_count: 0,
+        }
+    }
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::state;
+    impl From<state::Model> for MarketStatus {
+        fn from(state: state::Model) -> Self {
+            let chain_head = state.chain_head.map(|block| block as u64);
+
+            Self {
+                market_id: state.market_id,
+                latest_processed_block: state.latest_processed_block as u64,
+                chain_head,
+                lag_blocks: chain_head
+                    .map(|block| block.saturating_sub(state.latest_processed_block as u64)),
+                last_event_at: state.last_event_at,
+                mode: state.mode.into(),
+                last_error: state.last_error,
+                reconnect_count: state.reconnect_count as u32,
+                updated_at: state.status_updated_at.unwrap_or(state.timestamp),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "with-proto")]
+mod with_proto {
+    use super::*;
+    use sparker_proto::types as proto;
+
+    impl From<MarketStatus> for proto::MarketStatus {
+        fn from(status: MarketStatus) -> Self {
+            Self {
+                market_id: status.market_id,
+                latest_processed_block: status.latest_processed_block,
+                chain_head: status.chain_head,
+                lag_blocks: status.lag_blocks,
+                last_event_at: status
+                    .last_event_at
+                    .map(|last_event_at| last_event_at.and_utc().timestamp() as u64),
+                mode: proto::IndexerMode::from(status.mode) as i32,
+                last_error: status.last_error,
+                reconnect_count: status.reconnect_count,
+                updated_at: status.updated_at.and_utc().timestamp() as u64,
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// What the indexer of a market is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub enum IndexerMode {
+    /// Processing historical blocks, the market is behind the chain.
+    CatchingUp,
+    /// Following new blocks as they are produced.
+    Live,
+}
+
+impl fmt::Display for IndexerMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::sea_orm_active_enums;
+
+    impl From<sea_orm_active_enums::IndexerMode> for IndexerMode {
+        fn from(mode: sea_orm_active_enums::IndexerMode) -> Self {
+            match mode {
+                sea_orm_active_enums::IndexerMode::CatchingUp => IndexerMode::CatchingUp,
+                sea_orm_active_enums::IndexerMode::Live => IndexerMode::Live,
+            }
+        }
+    }
+
+    impl From<IndexerMode> for sea_orm_active_enums::IndexerMode {
+        fn from(mode: IndexerMode) -> Self {
+            match mode {
+                IndexerMode::CatchingUp => sea_orm_active_enums::IndexerMode::CatchingUp,
+                IndexerMode::Live => sea_orm_active_enums::IndexerMode::Live,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "with-proto")]
+mod with_proto {
+    use super::*;
+    use sparker_proto::types as proto;
+
+    impl From<proto::IndexerMode> for IndexerMode {
+        fn from(mode: proto::IndexerMode) -> Self {
+            match mode {
+                proto::IndexerMode::CatchingUp => IndexerMode::CatchingUp,
+                proto::IndexerMode::Live => IndexerMode::Live,
+            }
+        }
+    }
+
+    impl From<IndexerMode> for proto::IndexerMode {
+        fn from(mode: IndexerMode) -> Self {
+            match mode {
+                IndexerMode::CatchingUp => proto::IndexerMode::CatchingUp,
+                IndexerMode::Live => proto::IndexerMode::Live,
+            }
+        }
+    }
+}
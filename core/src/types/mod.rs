@@ -1,5 +1,17 @@
+mod dead_letter;
+mod market;
+mod market_status;
+mod number;
 mod order;
+mod order_event;
+mod raw_event;
 mod trade;
 
+pub use dead_letter::*;
+pub use market::*;
+pub use market_status::*;
+pub use number::*;
 pub use order::*;
+pub use order_event::*;
+pub use raw_event::*;
 pub use trade::*;
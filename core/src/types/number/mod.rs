@@ -0,0 +1,336 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+mod u256;
+
+pub use u256::*;
+
+/// An unsigned 128-bit integer, the width of the amounts and prices of the market contract.
+///
+/// It's encoded as a decimal string in JSON and protobuf since most clients can't represent
+/// integers wider than 53 or 64 bits. The arithmetic is checked, a result which doesn't fit is
+/// an error rather than a truncated value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U128(pub u128);
+
+/// A value which can't be represented as a [`U128`] or a [`U256`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum U128Error {
+    /// The value is negative, fractional, too large or not a number at all.
+    OutOfRange(String),
+    /// The result of an operation doesn't fit in its type.
+    Overflow,
+}
+
+impl fmt::Display for U128Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            U128Error::OutOfRange(value) => {
+                write!(f, "{} is not an unsigned integer in range", value)
+            }
+            U128Error::Overflow => write!(f, "unsigned integer overflow"),
+        }
+    }
+}
+
+impl std::error::Error for U128Error {}
+
+impl U128 {
+    pub const ZERO: U128 = U128(0);
+
+    pub fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn checked_add(self, other: U128) -> Result<U128, U128Error> {
+        self.0
+            .checked_add(other.0)
+            .map(U128)
+            .ok_or(U128Error::Overflow)
+    }
+
+    pub fn checked_sub(self, other: U128) -> Result<U128, U128Error> {
+        self.0
+            .checked_sub(other.0)
+            .map(U128)
+            .ok_or(U128Error::Overflow)
+    }
+
+    pub fn checked_mul(self, other: U128) -> Result<U128, U128Error> {
+        self.0
+            .checked_mul(other.0)
+            .map(U128)
+            .ok_or(U128Error::Overflow)
+    }
+
+    pub fn checked_div(self, other: U128) -> Result<U128, U128Error> {
+        self.0
+            .checked_div(other.0)
+            .map(U128)
+            .ok_or(U128Error::Overflow)
+    }
+
+    pub fn saturating_sub(self, other: U128) -> U128 {
+        U128(self.0.saturating_sub(other.0))
+    }
+
+    /// Formats the value scaled down by the given number of decimals, e.g. `1500` with 3
+    /// decimals is `1.5`.
+    ///
+    /// The formatting is exact, it's done on the digits rather than through a float. Trailing
+    /// zeros of the fraction are dropped.
+    pub fn to_decimal(&self, decimals: u32) -> String {
+        let decimals = decimals as usize;
+        let digits = format!("{:0>width$}", self.0, width = decimals + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+
+        match fraction.trim_end_matches('0') {
+            "" => integer.to_string(),
+            fraction => format!("{}.{}", integer, fraction),
+        }
+    }
+}
+
+impl From<u128> for U128 {
+    fn from(value: u128) -> Self {
+        Self(value)
+    }
+}
+
+impl From<u64> for U128 {
+    fn from(value: u64) -> Self {
+        Self(value as u128)
+    }
+}
+
+impl From<U128> for u128 {
+    fn from(value: U128) -> Self {
+        value.0
+    }
+}
+
+impl FromStr for U128 {
+    type Err = U128Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `u128` accepts a leading `+`, which isn't a canonical encoding
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(U128Error::OutOfRange(s.to_string()));
+        }
+
+        s.parse::<u128>()
+            .map(U128)
+            .map_err(|_| U128Error::OutOfRange(s.to_string()))
+    }
+}
+
+impl fmt::Display for U128 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for U128 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for U128 {
+    /// Accepts decimal strings as well as integers, which older payloads were encoded with.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = U128;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an unsigned 128-bit integer or its decimal string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<U128, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<U128, E> {
+                Ok(U128::from(v))
+            }
+
+            fn visit_u128<E: de::Error>(self, v: u128) -> Result<U128, E> {
+                Ok(U128(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<U128, E> {
+                u64::try_from(v)
+                    .map(U128::from)
+                    .map_err(|_| E::custom(U128Error::OutOfRange(v.to_string())))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(feature = "with-utoipa")]
+mod with_utoipa {
+    use super::*;
+    use utoipa::openapi::{schema::Type, ObjectBuilder, RefOr, Schema};
+
+    impl utoipa::PartialSchema for U128 {
+        fn schema() -> RefOr<Schema> {
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .pattern(Some("^[0-9]+$"))
+                .description(Some("Unsigned 128-bit integer as a decimal string"))
+                .examples(["1000000000"])
+                .into()
+        }
+    }
+
+    impl utoipa::ToSchema for U128 {}
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sea_orm::{prelude::BigDecimal, DbErr};
+
+    impl From<U128> for BigDecimal {
+        fn from(value: U128) -> Self {
+            BigDecimal::from(value.0)
+        }
+    }
+
+    impl TryFrom<BigDecimal> for U128 {
+        type Error = U128Error;
+
+        fn try_from(value: BigDecimal) -> Result<Self, Self::Error> {
+            if !value.is_integer() {
+                return Err(U128Error::OutOfRange(value.to_string()));
+            }
+
+            let (digits, _) = value.with_scale(0).into_bigint_and_exponent();
+            u128::try_from(&digits)
+                .map(U128)
+                .map_err(|_| U128Error::OutOfRange(value.to_string()))
+        }
+    }
+
+    /// A stored value which can't be represented is a type error of the column.
+    impl From<U128Error> for DbErr {
+        fn from(e: U128Error) -> Self {
+            DbErr::Type(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: U128 = U128(u128::MAX);
+
+    #[test]
+    fn round_trips_the_max_value_through_json() {
+        let json = serde_json::to_string(&MAX).unwrap();
+        assert_eq!(json, "\"340282366920938463463374607431768211455\"");
+        assert_eq!(serde_json::from_str::<U128>(&json).unwrap(), MAX);
+
+        // Integers of older payloads, as wide as the JSON parser reads them
+        assert_eq!(
+            serde_json::from_str::<U128>("18446744073709551615").unwrap(),
+            U128::from(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn rejects_the_strings_which_are_not_in_range() {
+        for value in [
+            "",
+            "+1",
+            "-1",
+            "1.5",
+            "1e3",
+            " 1",
+            "340282366920938463463374607431768211456",
+        ] {
+            assert_eq!(
+                value.parse::<U128>(),
+                Err(U128Error::OutOfRange(value.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn fails_the_operations_which_overflow() {
+        assert_eq!(MAX.checked_add(U128(1)), Err(U128Error::Overflow));
+        assert_eq!(MAX.checked_add(U128::ZERO), Ok(MAX));
+        assert_eq!(MAX.checked_mul(U128(2)), Err(U128Error::Overflow));
+        assert_eq!(MAX.checked_mul(U128(1)), Ok(MAX));
+        assert_eq!(U128::ZERO.checked_sub(U128(1)), Err(U128Error::Overflow));
+        assert_eq!(MAX.checked_sub(MAX), Ok(U128::ZERO));
+        assert_eq!(MAX.checked_div(U128::ZERO), Err(U128Error::Overflow));
+        assert_eq!(U128(1).saturating_sub(MAX), U128::ZERO);
+    }
+
+    #[test]
+    fn formats_the_value_as_a_decimal() {
+        assert_eq!(U128::ZERO.to_decimal(0), "0");
+        assert_eq!(U128::ZERO.to_decimal(9), "0");
+        assert_eq!(U128(1).to_decimal(0), "1");
+        assert_eq!(U128(1).to_decimal(9), "0.000000001");
+        assert_eq!(MAX.to_decimal(0), "340282366920938463463374607431768211455");
+        assert_eq!(
+            MAX.to_decimal(18),
+            "340282366920938463463.374607431768211455"
+        );
+        assert_eq!(
+            MAX.to_decimal(39),
+            "0.340282366920938463463374607431768211455"
+        );
+        assert_eq!(
+            MAX.to_decimal(42),
+            "0.000340282366920938463463374607431768211455"
+        );
+    }
+
+    #[cfg(feature = "with-sea")]
+    mod with_sea {
+        use super::*;
+        use sea_orm::{prelude::BigDecimal, Value};
+
+        #[test]
+        fn round_trips_the_max_value_through_big_decimal() {
+            let decimal = BigDecimal::from(MAX);
+            assert_eq!(decimal.to_string(), MAX.to_string());
+            assert_eq!(U128::try_from(decimal).unwrap(), MAX);
+
+            let too_large = BigDecimal::from(MAX) + BigDecimal::from(1);
+            assert!(U128::try_from(too_large).is_err());
+            assert!(U128::try_from(BigDecimal::from(-1)).is_err());
+            assert!("0.5"
+                .parse::<BigDecimal>()
+                .map(U128::try_from)
+                .unwrap()
+                .is_err());
+        }
+
+        #[test]
+        fn round_trips_the_max_value_through_a_numeric_column() {
+            // Bound as the value of a NUMERIC(39, 0) column
+            let value = Value::from(BigDecimal::from(MAX));
+            let (digits, scale) = value.unwrap::<BigDecimal>().as_bigint_and_exponent();
+            assert_eq!(scale, 0);
+            assert_eq!(digits.to_string().len(), 39);
+
+            // Read back as the column is returned, with or without a scale
+            for numeric in [
+                "340282366920938463463374607431768211455",
+                "340282366920938463463374607431768211455.000",
+            ] {
+                let decimal = numeric.parse::<BigDecimal>().unwrap();
+                assert_eq!(U128::try_from(decimal).unwrap(), MAX);
+            }
+        }
+    }
+}
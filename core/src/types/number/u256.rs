@@ -0,0 +1,238 @@
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+use super::{U128Error, U128};
+
+/// An unsigned 256-bit integer, wide enough for the product of two [`U128`]s such as the quote
+/// volume of a fill.
+///
+/// It's encoded as a decimal string like [`U128`]. The arithmetic is checked as well.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U256(pub primitive_types::U256);
+
+impl U256 {
+    pub const ZERO: U256 = U256(primitive_types::U256::zero());
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    /// Multiplies two [`U128`]s, the product always fits in 256 bits.
+    pub fn product(a: U128, b: U128) -> U256 {
+        U256(primitive_types::U256::from(a.0) * primitive_types::U256::from(b.0))
+    }
+
+    pub fn checked_add(self, other: U256) -> Result<U256, U128Error> {
+        self.0
+            .checked_add(other.0)
+            .map(U256)
+            .ok_or(U128Error::Overflow)
+    }
+
+    /// Divides the value by a [`U128`], the quotient has to fit in a [`U128`].
+    pub fn checked_div(self, other: U128) -> Result<U128, U128Error> {
+        let quotient = self
+            .0
+            .checked_div(primitive_types::U256::from(other.0))
+            .ok_or(U128Error::Overflow)?;
+
+        u128::try_from(quotient)
+            .map(U128)
+            .map_err(|_| U128Error::Overflow)
+    }
+}
+
+impl From<U128> for U256 {
+    fn from(value: U128) -> Self {
+        Self(primitive_types::U256::from(value.0))
+    }
+}
+
+impl FromStr for U256 {
+    type Err = U128Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(U128Error::OutOfRange(s.to_string()));
+        }
+
+        primitive_types::U256::from_dec_str(s)
+            .map(U256)
+            .map_err(|_| U128Error::OutOfRange(s.to_string()))
+    }
+}
+
+impl fmt::Display for U256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Serialize for U256 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for U256 {
+    /// Accepts decimal strings as well as integers, like [`U128`].
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl de::Visitor<'_> for Visitor {
+            type Value = U256;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an unsigned 256-bit integer or its decimal string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<U256, E> {
+                v.parse().map_err(E::custom)
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<U256, E> {
+                Ok(U256::from(U128::from(v)))
+            }
+
+            fn visit_u128<E: de::Error>(self, v: u128) -> Result<U256, E> {
+                Ok(U256::from(U128(v)))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<U256, E> {
+                u64::try_from(v)
+                    .map(|v| U256::from(U128::from(v)))
+                    .map_err(|_| E::custom(U128Error::OutOfRange(v.to_string())))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+#[cfg(feature = "with-utoipa")]
+mod with_utoipa {
+    use super::*;
+    use utoipa::openapi::{schema::Type, ObjectBuilder, RefOr, Schema};
+
+    impl utoipa::PartialSchema for U256 {
+        fn schema() -> RefOr<Schema> {
+            ObjectBuilder::new()
+                .schema_type(Type::String)
+                .pattern(Some("^[0-9]+$"))
+                .description(Some("Unsigned 256-bit integer as a decimal string"))
+                .examples(["1000000000000000000000000000000000000000"])
+                .into()
+        }
+    }
+
+    impl utoipa::ToSchema for U256 {}
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sea_orm::prelude::BigDecimal;
+
+    impl From<U256> for BigDecimal {
+        fn from(value: U256) -> Self {
+            value
+                .to_string()
+                .parse()
+                .expect("a decimal string is a valid BigDecimal")
+        }
+    }
+
+    impl TryFrom<BigDecimal> for U256 {
+        type Error = U128Error;
+
+        fn try_from(value: BigDecimal) -> Result<Self, Self::Error> {
+            if !value.is_integer() {
+                return Err(U128Error::OutOfRange(value.to_string()));
+            }
+
+            let (digits, _) = value.with_scale(0).into_bigint_and_exponent();
+            digits
+                .to_string()
+                .parse()
+                .map_err(|_| U128Error::OutOfRange(value.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn max() -> U256 {
+        U256(primitive_types::U256::MAX)
+    }
+
+    /// The volume of the largest fill of the largest price.
+    fn max_product() -> U256 {
+        U256::product(U128(u128::MAX), U128(u128::MAX))
+    }
+
+    #[test]
+    fn round_trips_the_max_values_through_json() {
+        for value in [max(), max_product(), U256::from(U128(u128::MAX))] {
+            let json = serde_json::to_string(&value).unwrap();
+            assert_eq!(json, format!("\"{}\"", value));
+            assert_eq!(serde_json::from_str::<U256>(&json).unwrap(), value);
+        }
+        assert_eq!(
+            max().to_string(),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639935"
+        );
+    }
+
+    #[test]
+    fn fails_the_operations_which_overflow() {
+        let one = U256::from(U128(1));
+        assert_eq!(max().checked_add(one), Err(U128Error::Overflow));
+        assert_eq!(
+            max_product().checked_add(one),
+            Ok(U256(max_product().0 + 1))
+        );
+
+        // The quotient has to fit in a U128
+        assert_eq!(
+            max_product().checked_div(U128(u128::MAX)),
+            Ok(U128(u128::MAX))
+        );
+        assert_eq!(
+            max_product().checked_div(U128(u128::MAX - 1)),
+            Err(U128Error::Overflow)
+        );
+        assert_eq!(one.checked_div(U128::ZERO), Err(U128Error::Overflow));
+    }
+
+    #[cfg(feature = "with-sea")]
+    mod with_sea {
+        use super::*;
+        use sea_orm::{prelude::BigDecimal, Value};
+
+        #[test]
+        fn round_trips_the_max_value_through_big_decimal() {
+            let decimal = BigDecimal::from(max());
+            assert_eq!(decimal.to_string(), max().to_string());
+            assert_eq!(U256::try_from(decimal).unwrap(), max());
+
+            let too_large = BigDecimal::from(max()) + BigDecimal::from(1);
+            assert!(U256::try_from(too_large).is_err());
+            assert!(U256::try_from(BigDecimal::from(-1)).is_err());
+        }
+
+        #[test]
+        fn round_trips_the_max_value_through_a_numeric_column() {
+            // Bound as the value of a NUMERIC(78, 0) column
+            let value = Value::from(BigDecimal::from(max()));
+            let (digits, scale) = value.unwrap::<BigDecimal>().as_bigint_and_exponent();
+            assert_eq!(scale, 0);
+            assert_eq!(digits.to_string().len(), 78);
+
+            let numeric = format!("{}.00", max());
+            let decimal = numeric.parse::<BigDecimal>().unwrap();
+            assert_eq!(U256::try_from(decimal).unwrap(), max());
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
+use crate::{Market, U128Error, U128, U256};
+
 mod order_type;
 mod status;
 
@@ -15,39 +17,70 @@ pub struct Order {
     pub order_type: OrderType,
     pub user: String,
     pub asset: String,
-    pub amount: u64,
-    pub price: u64,
+    /// Amount the order was opened with.
+    pub initial_amount: U128,
+    /// Amount matched by trades so far.
+    pub filled_amount: U128,
+    /// Amount which is still open.
+    pub remaining_amount: U128,
+    pub price: U128,
+    /// Volume-weighted average price of the fills.
+    pub average_fill_price: U128,
+    /// Sum of the price times the size of the fills, the average fill price is derived from it.
+    pub fill_volume: U256,
     pub status: OrderStatus,
     pub block_number: u64,
     pub timestamp: NaiveDateTime,
     pub market_id: String,
+    /// Amounts and prices as decimal strings, only if they were requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<OrderDecimals>,
+}
+
+/// Amounts and prices of an order scaled by the decimals of its market.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub struct OrderDecimals {
+    pub initial_amount: String,
+    pub filled_amount: String,
+    pub remaining_amount: String,
+    pub price: String,
+    pub average_fill_price: String,
 }
 
 #[cfg(feature = "with-sea")]
 mod with_sea {
     use super::*;
     use sparker_entity::order;
-    impl From<order::Model> for Order {
-        fn from(order: order::Model) -> Self {
-            Self {
+    impl TryFrom<order::Model> for Order {
+        type Error = U128Error;
+
+        fn try_from(order: order::Model) -> Result<Self, Self::Error> {
+            Ok(Self {
                 tx_id: order.tx_id,
                 order_id: order.order_id,
                 order_type: order.order_type.into(),
                 user: order.user,
                 asset: order.asset,
-                amount: order.amount as u64,
-                price: order.price as u64,
+                initial_amount: order.initial_amount.try_into()?,
+                filled_amount: order.filled_amount.try_into()?,
+                remaining_amount: order.remaining_amount.try_into()?,
+                price: order.price.try_into()?,
+                average_fill_price: order.average_fill_price.try_into()?,
+                fill_volume: order.fill_volume.try_into()?,
                 status: order.status.into(),
                 block_number: order.block_number as u64,
                 timestamp: order.timestamp,
                 market_id: order.market_id,
-            }
+                decimals: None,
+            })
         }
     }
 
     impl Order {
         pub fn from_payload(payload: &str) -> Result<Self, serde_json::Error> {
-            serde_json::from_str::<sparker_entity::order::Model>(payload).map(Self::from)
+            let order = serde_json::from_str::<sparker_entity::order::Model>(payload)?;
+            Self::try_from(order).map_err(serde::de::Error::custom)
         }
     }
 }
@@ -58,26 +91,33 @@ mod with_proto {
     use chrono::DateTime;
     use sparker_proto::types as proto;
 
-    impl From<proto::Order> for Order {
-        fn from(order: proto::Order) -> Self {
+    impl TryFrom<proto::Order> for Order {
+        type Error = U128Error;
+
+        fn try_from(order: proto::Order) -> Result<Self, Self::Error> {
             let order_type = proto::OrderType::from_repr(order.order_type).unwrap();
             let status = proto::OrderStatus::from_repr(order.status).unwrap();
 
-            Self {
+            Ok(Self {
                 tx_id: order.tx_id,
                 order_id: order.order_id,
                 order_type: order_type.into(),
                 user: order.user,
                 asset: order.asset,
-                amount: order.amount,
-                price: order.price,
+                initial_amount: order.initial_amount.parse()?,
+                filled_amount: order.filled_amount.parse()?,
+                remaining_amount: order.remaining_amount.parse()?,
+                price: order.price.parse()?,
+                average_fill_price: order.average_fill_price.parse()?,
+                fill_volume: order.fill_volume.parse()?,
                 status: status.into(),
                 block_number: order.block_number,
                 timestamp: DateTime::from_timestamp(order.timestamp as i64, 0)
                     .unwrap()
                     .naive_utc(),
                 market_id: order.market_id,
-            }
+                decimals: order.decimals.map(OrderDecimals::from),
+            })
         }
     }
 
@@ -89,12 +129,41 @@ mod with_proto {
                 order_type: proto::OrderType::from(order.order_type) as i32,
                 user: order.user,
                 asset: order.asset,
-                amount: order.amount,
-                price: order.price,
+                initial_amount: order.initial_amount.to_string(),
+                filled_amount: order.filled_amount.to_string(),
+                remaining_amount: order.remaining_amount.to_string(),
+                price: order.price.to_string(),
+                average_fill_price: order.average_fill_price.to_string(),
+                fill_volume: order.fill_volume.to_string(),
                 status: proto::OrderStatus::from(order.status) as i32,
                 block_number: order.block_number,
                 timestamp: order.timestamp.and_utc().timestamp() as u64,
                 market_id: order.market_id,
+                decimals: order.decimals.map(proto::OrderDecimals::from),
+            }
+        }
+    }
+
+    impl From<proto::OrderDecimals> for OrderDecimals {
+        fn from(decimals: proto::OrderDecimals) -> Self {
+            Self {
+                initial_amount: decimals.initial_amount,
+                filled_amount: decimals.filled_amount,
+                remaining_amount: decimals.remaining_amount,
+                price: decimals.price,
+                average_fill_price: decimals.average_fill_price,
+            }
+        }
+    }
+
+    impl From<OrderDecimals> for proto::OrderDecimals {
+        fn from(decimals: OrderDecimals) -> Self {
+            Self {
+                initial_amount: decimals.initial_amount,
+                filled_amount: decimals.filled_amount,
+                remaining_amount: decimals.remaining_amount,
+                price: decimals.price,
+                average_fill_price: decimals.average_fill_price,
             }
         }
     }
@@ -104,11 +173,51 @@ impl Order {
     pub fn is_active(&self) -> bool {
         self.status == OrderStatus::New || self.status == OrderStatus::PartiallyMatched
     }
+
+    /// Applies a fill of the given size and price to the amounts of the order.
+    ///
+    /// The volume of the fills is summed exactly, the average fill price is derived from it
+    /// so it's rounded down once rather than on every fill. The status is left untouched since
+    /// it depends on the limit type of the trade.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the filled amount overflows, the order is left untouched then.
+    pub fn fill(&mut self, size: U128, price: U128) -> Result<(), U128Error> {
+        let filled_amount = self.filled_amount.checked_add(size)?;
+        let fill_volume = self.fill_volume.checked_add(U256::product(price, size))?;
+        if !filled_amount.is_zero() {
+            self.average_fill_price = fill_volume.checked_div(filled_amount)?;
+        }
+
+        self.filled_amount = filled_amount;
+        self.remaining_amount = self.remaining_amount.saturating_sub(size);
+        self.fill_volume = fill_volume;
+
+        Ok(())
+    }
+
+    /// Adds the amounts and prices of the order as decimal strings, scaled by the decimals
+    /// of its market. The raw amounts and prices are kept.
+    pub fn with_decimals(mut self, market: &Market) -> Self {
+        self.decimals = Some(OrderDecimals {
+            initial_amount: market.format_amount(self.initial_amount),
+            filled_amount: market.format_amount(self.filled_amount),
+            remaining_amount: market.format_amount(self.remaining_amount),
+            price: market.format_price(self.price),
+            average_fill_price: market.format_price(self.average_fill_price),
+        });
+
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateOrder {
     pub order_id: String,
-    pub amount: Option<u64>,
+    pub filled_amount: Option<U128>,
+    pub remaining_amount: Option<U128>,
+    pub average_fill_price: Option<U128>,
+    pub fill_volume: Option<U256>,
     pub status: OrderStatus,
 }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub enum OrderEventType {
+    Open,
+    PartialFill,
+    Fill,
+    Cancel,
+    /// The unfilled remainder of an IOC or MKT order expired.
+    Expire,
+    /// The order couldn't be reconciled with the trades.
+    Fail,
+}
+
+impl fmt::Display for OrderEventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::sea_orm_active_enums;
+
+    impl From<sea_orm_active_enums::OrderEventType> for OrderEventType {
+        fn from(event_type: sea_orm_active_enums::OrderEventType) -> Self {
+            match event_type {
+                sea_orm_active_enums::OrderEventType::Open => OrderEventType::Open,
+                sea_orm_active_enums::OrderEventType::PartialFill => OrderEventType::PartialFill,
+                sea_orm_active_enums::OrderEventType::Fill => OrderEventType::Fill,
+                sea_orm_active_enums::OrderEventType::Cancel => OrderEventType::Cancel,
+                sea_orm_active_enums::OrderEventType::Expire => OrderEventType::Expire,
+                sea_orm_active_enums::OrderEventType::Fail => OrderEventType::Fail,
+            }
+        }
+    }
+
+    impl From<OrderEventType> for sea_orm_active_enums::OrderEventType {
+        fn from(event_type: OrderEventType) -> Self {
+            match event_type {
+                OrderEventType::Open => sea_orm_active_enums::OrderEventType::Open,
+                OrderEventType::PartialFill => sea_orm_active_enums::OrderEventType::PartialFill,
+                OrderEventType::Fill => sea_orm_active_enums::OrderEventType::Fill,
+                OrderEventType::Cancel => sea_orm_active_enums::OrderEventType::Cancel,
+                OrderEventType::Expire => sea_orm_active_enums::OrderEventType::Expire,
+                OrderEventType::Fail => sea_orm_active_enums::OrderEventType::Fail,
+            }
+        }
+    }
+}
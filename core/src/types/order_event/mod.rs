@@ -0,0 +1,47 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{OrderStatus, U128Error, U128};
+
+mod event_type;
+
+pub use event_type::*;
+
+/// A state transition of an order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub struct OrderEvent {
+    pub order_id: String,
+    pub event_type: OrderEventType,
+    pub tx_id: String,
+    pub block_number: u64,
+    pub timestamp: NaiveDateTime,
+    pub amount_before: U128,
+    pub amount_after: U128,
+    /// Status of the order after the transition.
+    pub status: OrderStatus,
+    pub market_id: String,
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::order_event;
+    impl TryFrom<order_event::Model> for OrderEvent {
+        type Error = U128Error;
+
+        fn try_from(event: order_event::Model) -> Result<Self, Self::Error> {
+            Ok(Self {
+                order_id: event.order_id,
+                event_type: event.event_type.into(),
+                tx_id: event.tx_id,
+                block_number: event.block_number as u64,
+                timestamp: event.timestamp,
+                amount_before: event.amount_before.try_into()?,
+                amount_after: event.amount_after.try_into()?,
+                status: event.status.into(),
+                market_id: event.market_id,
+            })
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// An event as it was received from the chain, kept in the journal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawEvent {
+    pub market_id: String,
+    pub block_number: u64,
+    pub block_hash: String,
+    pub transaction_hash: String,
+    pub transaction_index: u64,
+    pub log_index: u64,
+    pub payload: String,
+}
+
+#[cfg(feature = "with-sea")]
+mod with_sea {
+    use super::*;
+    use sparker_entity::raw_event;
+    impl From<raw_event::Model> for RawEvent {
+        fn from(raw_event: raw_event::Model) -> Self {
+            Self {
+                market_id: raw_event.market_id,
+                block_number: raw_event.block_number as u64,
+                block_hash: raw_event.block_hash,
+                transaction_hash: raw_event.transaction_hash,
+                transaction_index: raw_event.transaction_index as u64,
+                log_index: raw_event.log_index as u64,
+                payload: raw_event.payload,
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
+use crate::{Market, U128Error, U128};
+
 mod limit_type;
 
 pub use limit_type::*;
@@ -13,31 +15,58 @@ pub struct Trade {
     pub order_id: String,
     pub limit_type: LimitType,
     pub user: String,
-    pub size: u64,
-    pub price: u64,
+    pub size: U128,
+    pub price: U128,
     pub block_number: u64,
     pub timestamp: NaiveDateTime,
     pub market_id: String,
+    /// Size and price as decimal strings, only if they were requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<TradeDecimals>,
+}
+
+/// Size and price of a trade scaled by the decimals of its market.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "with-utoipa", derive(utoipa::ToSchema))]
+pub struct TradeDecimals {
+    pub size: String,
+    pub price: String,
+}
+
+impl Trade {
+    /// Adds the size and price of the trade as decimal strings, scaled by the decimals of its
+    /// market. The raw size and price are kept.
+    pub fn with_decimals(mut self, market: &Market) -> Self {
+        self.decimals = Some(TradeDecimals {
+            size: market.format_amount(self.size),
+            price: market.format_price(self.price),
+        });
+
+        self
+    }
 }
 
 #[cfg(feature = "with-sea")]
 mod with_sea {
     use super::*;
     use sparker_entity::trade;
-    impl From<trade::Model> for Trade {
-        fn from(trade: trade::Model) -> Self {
-            Self {
+    impl TryFrom<trade::Model> for Trade {
+        type Error = U128Error;
+
+        fn try_from(trade: trade::Model) -> Result<Self, Self::Error> {
+            Ok(Self {
                 tx_id: trade.tx_id,
                 trade_id: trade.trade_id,
                 order_id: trade.order_id,
                 limit_type: trade.limit_type.into(),
                 user: trade.user,
-                size: trade.size as u64,
-                price: trade.price as u64,
+                size: trade.size.try_into()?,
+                price: trade.price.try_into()?,
                 block_number: trade.block_number as u64,
                 timestamp: trade.timestamp,
                 market_id: trade.market_id,
-            }
+                decimals: None,
+            })
         }
     }
 }
@@ -48,24 +77,27 @@ mod with_proto {
     use chrono::DateTime;
     use sparker_proto::types as proto;
 
-    impl From<proto::Trade> for Trade {
-        fn from(trade: proto::Trade) -> Self {
+    impl TryFrom<proto::Trade> for Trade {
+        type Error = U128Error;
+
+        fn try_from(trade: proto::Trade) -> Result<Self, Self::Error> {
             let limit_type = proto::LimitType::from_repr(trade.limit_type).unwrap();
 
-            Self {
+            Ok(Self {
                 tx_id: trade.tx_id,
                 trade_id: trade.trade_id,
                 order_id: trade.order_id,
                 limit_type: limit_type.into(),
                 user: trade.user,
-                size: trade.size,
-                price: trade.price,
+                size: trade.size.parse()?,
+                price: trade.price.parse()?,
                 block_number: trade.block_number,
                 timestamp: DateTime::from_timestamp(trade.timestamp as i64, 0)
                     .unwrap()
                     .naive_utc(),
                 market_id: trade.market_id,
-            }
+                decimals: trade.decimals.map(TradeDecimals::from),
+            })
         }
     }
 
@@ -77,11 +109,30 @@ mod with_proto {
                 order_id: trade.order_id,
                 limit_type: proto::LimitType::from(trade.limit_type) as i32,
                 user: trade.user,
-                size: trade.size,
-                price: trade.price,
+                size: trade.size.to_string(),
+                price: trade.price.to_string(),
                 block_number: trade.block_number,
                 timestamp: trade.timestamp.and_utc().timestamp() as u64,
                 market_id: trade.market_id,
+                decimals: trade.decimals.map(proto::TradeDecimals::from),
+            }
+        }
+    }
+
+    impl From<proto::TradeDecimals> for TradeDecimals {
+        fn from(decimals: proto::TradeDecimals) -> Self {
+            Self {
+                size: decimals.size,
+                price: decimals.price,
+            }
+        }
+    }
+
+    impl From<TradeDecimals> for proto::TradeDecimals {
+        fn from(decimals: TradeDecimals) -> Self {
+            Self {
+                size: decimals.size,
+                price: decimals.price,
             }
         }
     }
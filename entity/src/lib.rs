@@ -2,7 +2,13 @@
 
 pub mod prelude;
 
+pub mod block;
+pub mod dead_letter;
+pub mod market;
 pub mod order;
+pub mod order_event;
+pub mod order_undo;
+pub mod raw_event;
 pub mod sea_orm_active_enums;
 pub mod state;
 pub mod trade;
@@ -16,20 +16,38 @@ pub struct Model {
     pub order_type: OrderType,
     pub user: String,
     pub asset: String,
-    pub amount: i64,
-    pub price: i64,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub remaining_amount: BigDecimal,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub price: BigDecimal,
     pub status: OrderStatus,
     pub block_number: i64,
     pub timestamp: DateTime,
     pub market_id: String,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub initial_amount: BigDecimal,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub filled_amount: BigDecimal,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub average_fill_price: BigDecimal,
+    #[sea_orm(column_type = "Decimal(Some((78, 0)))")]
+    pub fill_volume: BigDecimal,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::order_event::Entity")]
+    OrderEvent,
     #[sea_orm(has_many = "super::trade::Entity")]
     Trade,
 }
 
+impl Related<super::order_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::OrderEvent.def()
+    }
+}
+
 impl Related<super::trade::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Trade.def()
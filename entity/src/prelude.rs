@@ -1,5 +1,11 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::block::Entity as Block;
+pub use super::dead_letter::Entity as DeadLetter;
+pub use super::market::Entity as Market;
 pub use super::order::Entity as Order;
+pub use super::order_event::Entity as OrderEvent;
+pub use super::order_undo::Entity as OrderUndo;
+pub use super::raw_event::Entity as RawEvent;
 pub use super::state::Entity as State;
 pub use super::trade::Entity as Trade;
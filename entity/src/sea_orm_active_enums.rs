@@ -3,6 +3,25 @@
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    utoipa :: ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "indexer_mode")]
+#[serde(rename_all = "snake_case")]
+pub enum IndexerMode {
+    #[sea_orm(string_value = "catching_up")]
+    CatchingUp,
+    #[sea_orm(string_value = "live")]
+    Live,
+}
 #[derive(
     Debug,
     Clone,
@@ -37,6 +56,33 @@ pub enum LimitType {
     Deserialize,
     utoipa :: ToSchema,
 )]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "order_event_type")]
+#[serde(rename_all = "snake_case")]
+pub enum OrderEventType {
+    #[sea_orm(string_value = "open")]
+    Open,
+    #[sea_orm(string_value = "partial_fill")]
+    PartialFill,
+    #[sea_orm(string_value = "fill")]
+    Fill,
+    #[sea_orm(string_value = "cancel")]
+    Cancel,
+    #[sea_orm(string_value = "expire")]
+    Expire,
+    #[sea_orm(string_value = "fail")]
+    Fail,
+}
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    utoipa :: ToSchema,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "order_status")]
 #[serde(rename_all = "snake_case")]
 pub enum OrderStatus {
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+use super::sea_orm_active_enums::IndexerMode;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +13,13 @@ pub struct Model {
     pub market_id: String,
     pub latest_processed_block: i64,
     pub timestamp: DateTime,
+    pub chain_head: Option<i64>,
+    pub last_event_at: Option<DateTime>,
+    pub mode: IndexerMode,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    pub reconnect_count: i32,
+    pub status_updated_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -13,8 +13,10 @@ pub struct Model {
     #[sea_orm(unique)]
     pub trade_id: String,
     pub order_id: String,
-    pub size: i64,
-    pub price: i64,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub size: BigDecimal,
+    #[sea_orm(column_type = "Decimal(Some((39, 0)))")]
+    pub price: BigDecimal,
     pub limit_type: LimitType,
     pub user: String,
     pub block_number: i64,
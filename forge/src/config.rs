@@ -1,35 +1,814 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use pangea_client::ChainId;
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::BufReader, path::Path};
+use std::{
+    collections::HashSet,
+    env,
+    fs::File,
+    io::BufReader,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::error::Error;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Number of blocks fetched at once while catching up, unless set by the config.
+const DEFAULT_BATCH_SIZE: u64 = 100_000;
+/// Number of windows of blocks fetched ahead while catching up, unless set by the config.
+const DEFAULT_CATCH_UP_CONCURRENCY: usize = 4;
+/// Address of the metrics and health endpoints, unless set by the config.
+const DEFAULT_METRICS_ADDR: &str = "0.0.0.0:9090";
+/// Time after which a dispatcher which doesn't make progress makes forge unhealthy, unless set
+/// by the config.
+const DEFAULT_STALL_TIMEOUT_SECS: u64 = 300;
+/// Time given to the markets to commit their last blocks on shutdown, unless set by the config.
+/// It's below the default termination grace period of a pod.
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 25;
+
+/// Command line of forge, every option can be set by its environment variable as well.
+///
+/// The options override the values of the config file.
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Indexes the Spark markets into the orderbook database"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Path of the config file
+    #[arg(long, env = "CONFIG_PATH", default_value = "config.mainnet.json")]
+    pub config: PathBuf,
+
+    /// Network of the markets
+    #[arg(long, env = "NETWORK", value_enum)]
+    pub network: Option<Network>,
+
+    /// Source of the events
+    #[arg(long, env = "SOURCE", value_enum)]
+    pub source: Option<SourceKind>,
+
+    /// Fuel node url, the public node of the network if unset
+    #[arg(long, env = "FUEL_URL")]
+    pub fuel_url: Option<String>,
+
+    /// Pangea host url
+    #[arg(long, env = "PANGEA_HOST")]
+    pub pangea_host: Option<String>,
+
+    /// Pangea username
+    #[arg(long, env = "PANGEA_USERNAME", hide_env_values = true)]
+    pub pangea_username: Option<String>,
+
+    /// Pangea password
+    #[arg(long, env = "PANGEA_PASSWORD", hide_env_values = true)]
+    pub pangea_password: Option<String>,
+
+    /// Database url
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    pub database_url: Option<String>,
+
+    /// Number of blocks fetched at once while catching up
+    #[arg(long, env = "BATCH_SIZE")]
+    pub batch_size: Option<u64>,
+
+    /// Number of windows of blocks fetched ahead while catching up
+    #[arg(long, env = "CATCH_UP_CONCURRENCY")]
+    pub catch_up_concurrency: Option<usize>,
+
+    /// Markets to index, a single id or a comma-separated list (all the markets if unset).
+    /// They are the markets of the network if the config has none
+    #[arg(long = "market-id", env = "MARKET_ID", value_delimiter = ',')]
+    pub market_ids: Vec<String>,
+
+    /// Index only a shard of the markets, e.g. the first one out of 4
+    #[arg(long, env = "SHARD_INDEX", requires = "shard_count")]
+    pub shard_index: Option<usize>,
+
+    /// Number of shards the markets are split into
+    #[arg(long, env = "SHARD_COUNT", requires = "shard_index")]
+    pub shard_count: Option<usize>,
+
+    /// Replay events from a JSONL dump instead of Pangea
+    #[arg(long, env = "REPLAY_FILE")]
+    pub replay_file: Option<PathBuf>,
+
+    /// Directory of the cached Pangea batches, the cache is off if unset
+    #[arg(long, env = "CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Address of the metrics and health endpoints
+    #[arg(long, env = "METRICS_ADDR")]
+    pub metrics_addr: Option<SocketAddr>,
+
+    /// Seconds after which a dispatcher which doesn't make progress makes forge unhealthy
+    #[arg(long, env = "STALL_TIMEOUT_SECS")]
+    pub stall_timeout_secs: Option<u64>,
+
+    /// Seconds given to the markets to commit their last blocks on shutdown, keep it below
+    /// the termination grace period of the pod
+    #[arg(long, env = "SHUTDOWN_TIMEOUT_SECS")]
+    pub shutdown_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum Command {
+    /// Index the markets (default)
+    Run,
+
+    /// Apply the pending dead letters again and exit
+    Redrive,
+
+    /// Rebuild the orders and trades from the journaled events and exit
+    Rebuild,
+
+    /// Inspect or evict the cached Pangea batches
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum CacheCommand {
+    /// List the cached batches of the markets
+    List,
+
+    /// Evict the cached batches of the markets
+    Evict {
+        /// Evict only the batches which end at or after this block
+        #[arg(long, default_value_t = 0)]
+        from_block: i64,
+    },
+}
+
+/// Fuel network, which determines both the Pangea chain and the Fuel node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    /// Network of a legacy `CHAIN_ID`, which is the name of a Pangea chain.
+    fn from_chain_id(chain_id: &str) -> Result<Self, Error> {
+        match chain_id {
+            "FUEL" => Ok(Network::Mainnet),
+            "FUELTESTNET" => Ok(Network::Testnet),
+            _ => Err(Error::Config(format!(
+                "Unknown CHAIN_ID {}, use FUEL or FUELTESTNET, or --network or NETWORK",
+                chain_id
+            ))),
+        }
+    }
+
+    pub fn chain_id(&self) -> ChainId {
+        match self {
+            Network::Mainnet => ChainId::FUEL,
+            Network::Testnet => ChainId::FUELTESTNET,
+        }
+    }
+
+    pub fn fuel_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet.fuel.network",
+            Network::Testnet => "testnet.fuel.network",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+        }
+    }
+}
+
+/// Source of the events of the markets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// The Pangea indexer
+    #[default]
+    Pangea,
+
+    /// The logs of the market contracts read from a Fuel node
+    Fuel,
+}
+
+/// Backoff between the attempts to reconnect to the source of events.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReconnectPolicy {
+    /// Delay before the first attempt, in seconds
+    pub min_backoff_secs: u64,
+
+    /// Upper bound of the delay, which doubles after every failed attempt, in seconds
+    pub max_backoff_secs: u64,
+}
+
+impl ReconnectPolicy {
+    pub fn min_backoff(&self) -> Duration {
+        Duration::from_secs(self.min_backoff_secs)
+    }
+
+    pub fn max_backoff(&self) -> Duration {
+        Duration::from_secs(self.max_backoff_secs)
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            min_backoff_secs: 1,
+            max_backoff_secs: 32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketInfo {
     /// Market id
     pub id: String,
 
     /// Market name
     pub name: String,
+
+    /// Block to start indexing from, the Pangea start block if unset
+    #[serde(default)]
+    pub start_block: Option<i64>,
+
+    /// Number of blocks fetched at once while catching up, the global one if unset
+    #[serde(default)]
+    pub batch_size: Option<u64>,
+
+    /// Base asset, what's unset is read from the market contract
+    #[serde(default)]
+    pub base_asset: AssetInfo,
+
+    /// Quote asset, what's unset is read from the market contract
+    #[serde(default)]
+    pub quote_asset: AssetInfo,
+
+    /// Number of decimals of the prices, read from the market contract if unset
+    #[serde(default)]
+    pub price_precision: Option<u32>,
+}
+
+impl MarketInfo {
+    /// Market which isn't in the config, it's named by its id and read from its contract.
+    fn from_id(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            name: id.to_string(),
+            start_block: None,
+            batch_size: None,
+            base_asset: AssetInfo::default(),
+            quote_asset: AssetInfo::default(),
+            price_precision: None,
+        }
+    }
+}
+
+/// Asset of a market.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetInfo {
+    /// Asset id
+    #[serde(default)]
+    pub id: Option<String>,
+
+    /// Ticker of the asset, which is not known to the market contract
+    #[serde(default)]
+    pub symbol: Option<String>,
+
+    /// Number of decimals of the amounts
+    #[serde(default)]
+    pub decimals: Option<u32>,
+}
+
+impl AssetInfo {
+    /// Whether the market contract has to be read for the asset.
+    pub fn is_partial(&self) -> bool {
+        self.id.is_none() || self.decimals.is_none()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
+    /// Network of the markets
+    #[serde(default)]
+    pub network: Option<Network>,
+
+    /// Source of the events
+    #[serde(default)]
+    pub source: SourceKind,
+
+    /// Fuel node url, the public node of the network if unset
+    #[serde(default)]
+    pub fuel_url: Option<String>,
+
     /// Pangea host url
     pub pangea_host: String,
 
     /// Pangea start block
     pub pangea_start_block: i64,
 
+    /// Pangea username, usually set by the environment
+    #[serde(default)]
+    pub pangea_username: Option<String>,
+
+    /// Pangea password, usually set by the environment
+    #[serde(default)]
+    pub pangea_password: Option<String>,
+
+    /// Database url, usually set by the environment
+    #[serde(default)]
+    pub database_url: Option<String>,
+
+    /// Number of blocks fetched at once while catching up
+    #[serde(default = "default_batch_size")]
+    pub batch_size: u64,
+
+    /// Number of windows of blocks fetched ahead while catching up
+    #[serde(default = "default_catch_up_concurrency")]
+    pub catch_up_concurrency: usize,
+
+    /// Reconnect policy of the source of events
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+
+    /// Replay events from a JSONL dump instead of Pangea
+    #[serde(default)]
+    pub replay_file: Option<PathBuf>,
+
+    /// Directory of the cached Pangea batches, the cache is off if unset
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Address of the metrics and health endpoints
+    #[serde(default = "default_metrics_addr")]
+    pub metrics_addr: SocketAddr,
+
+    /// Seconds after which a dispatcher which doesn't make progress makes forge unhealthy
+    #[serde(default = "default_stall_timeout_secs")]
+    pub stall_timeout_secs: u64,
+
+    /// Seconds given to the markets to commit their last blocks on shutdown
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
     /// Markets
+    #[serde(default)]
     pub markets: Vec<MarketInfo>,
 }
 
+fn default_batch_size() -> u64 {
+    DEFAULT_BATCH_SIZE
+}
+
+fn default_catch_up_concurrency() -> usize {
+    DEFAULT_CATCH_UP_CONCURRENCY
+}
+
+fn default_metrics_addr() -> SocketAddr {
+    DEFAULT_METRICS_ADDR.parse().unwrap()
+}
+
+fn default_stall_timeout_secs() -> u64 {
+    DEFAULT_STALL_TIMEOUT_SECS
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    DEFAULT_SHUTDOWN_TIMEOUT_SECS
+}
+
 impl Config {
     pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
-        let file = File::open(path)?;
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| Error::Config(format!("Can't open {}: {}", path.display(), e)))?;
         let reader = BufReader::new(file);
-        let config: Config = serde_json::from_reader(reader)?;
+        let config: Config = serde_json::from_reader(reader)
+            .map_err(|e| Error::Config(format!("Invalid {}: {}", path.display(), e)))?;
 
         Ok(config)
     }
+
+    /// Builds the config of the command line.
+    ///
+    /// The config file is loaded first, then it's overridden by the command line options
+    /// and narrowed down to the selected markets.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a readable message if the config is incomplete or invalid.
+    pub fn build(cli: &Cli) -> Result<Self, Error> {
+        let mut config = Self::load(&cli.config)?;
+
+        if let Some(network) = cli.network {
+            config.network = Some(network);
+        }
+        // `CHAIN_ID` is kept for the deployments which predate the network setting
+        if config.network.is_none() {
+            config.network = env::var("CHAIN_ID")
+                .ok()
+                .map(|chain_id| Network::from_chain_id(&chain_id))
+                .transpose()?;
+        }
+        if let Some(source) = cli.source {
+            config.source = source;
+        }
+        if let Some(fuel_url) = &cli.fuel_url {
+            config.fuel_url = Some(fuel_url.clone());
+        }
+        if let Some(pangea_host) = &cli.pangea_host {
+            config.pangea_host = pangea_host.clone();
+        }
+        if let Some(pangea_username) = &cli.pangea_username {
+            config.pangea_username = Some(pangea_username.clone());
+        }
+        if let Some(pangea_password) = &cli.pangea_password {
+            config.pangea_password = Some(pangea_password.clone());
+        }
+        if let Some(database_url) = &cli.database_url {
+            config.database_url = Some(database_url.clone());
+        }
+        if let Some(batch_size) = cli.batch_size {
+            config.batch_size = batch_size;
+        }
+        if let Some(catch_up_concurrency) = cli.catch_up_concurrency {
+            config.catch_up_concurrency = catch_up_concurrency;
+        }
+        if let Some(replay_file) = &cli.replay_file {
+            config.replay_file = Some(replay_file.clone());
+        }
+        if let Some(cache_dir) = &cli.cache_dir {
+            config.cache_dir = Some(cache_dir.clone());
+        }
+        if let Some(metrics_addr) = cli.metrics_addr {
+            config.metrics_addr = metrics_addr;
+        }
+        if let Some(stall_timeout_secs) = cli.stall_timeout_secs {
+            config.stall_timeout_secs = stall_timeout_secs;
+        }
+        if let Some(shutdown_timeout_secs) = cli.shutdown_timeout_secs {
+            config.shutdown_timeout_secs = shutdown_timeout_secs;
+        }
+        // A network without configured markets indexes the ones given by id
+        if config.markets.is_empty() {
+            config.markets = cli
+                .market_ids
+                .iter()
+                .map(|id| MarketInfo::from_id(id))
+                .collect();
+        }
+
+        config.validate()?;
+        // The cache is managed without the database and the source of events
+        if !matches!(cli.command, Some(Command::Cache { .. })) {
+            config.validate_connections()?;
+        }
+        config.select_markets(&MarketSelection {
+            ids: cli.market_ids.clone(),
+            shard: cli.shard_index.zip(cli.shard_count),
+        })?;
+
+        Ok(config)
+    }
+
+    /// Checks that the config is complete and consistent.
+    fn validate(&self) -> Result<(), Error> {
+        if self.network.is_none() {
+            return Err(Error::Config(
+                "Network is not set, use --network or NETWORK".to_string(),
+            ));
+        }
+        if self.reconnect.min_backoff_secs == 0
+            || self.reconnect.min_backoff_secs > self.reconnect.max_backoff_secs
+        {
+            return Err(Error::Config(format!(
+                "Invalid reconnect policy: backoff from {}s to {}s",
+                self.reconnect.min_backoff_secs, self.reconnect.max_backoff_secs
+            )));
+        }
+        if self.catch_up_concurrency == 0 {
+            return Err(Error::Config("Catch-up concurrency is 0".to_string()));
+        }
+        if self.stall_timeout_secs == 0 {
+            return Err(Error::Config("Stall timeout is 0".to_string()));
+        }
+        if self.shutdown_timeout_secs == 0 {
+            return Err(Error::Config("Shutdown timeout is 0".to_string()));
+        }
+        if self.markets.is_empty() {
+            return Err(Error::Config(format!(
+                "No markets are configured for {}, add them to the config or use --market-id",
+                self.network().name()
+            )));
+        }
+
+        let mut ids = HashSet::new();
+        for market in self.markets.iter() {
+            if !is_hex_id(&market.id) {
+                return Err(Error::Config(format!(
+                    "Invalid id {} of market {}",
+                    market.id, market.name
+                )));
+            }
+            if !ids.insert(market.id.to_lowercase()) {
+                return Err(Error::Config(format!("Duplicate market {}", market.id)));
+            }
+            for asset in [&market.base_asset, &market.quote_asset] {
+                if let Some(id) = asset.id.as_deref().filter(|id| !is_hex_id(id)) {
+                    return Err(Error::Config(format!(
+                        "Invalid asset id {} of market {}",
+                        id, market.name
+                    )));
+                }
+            }
+            if self.batch_size(market) == 0 {
+                return Err(Error::Config(format!(
+                    "Batch size of market {} is 0",
+                    market.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks the settings of the database and of the source of events, which the cache
+    /// commands don't use.
+    fn validate_connections(&self) -> Result<(), Error> {
+        if self.database_url.is_none() {
+            return Err(Error::Config(
+                "Database url is not set, use --database-url or DATABASE_URL".to_string(),
+            ));
+        }
+        if self.replay_file.is_none() && self.source == SourceKind::Pangea {
+            if self.pangea_host.is_empty() {
+                return Err(Error::Config("Pangea host is empty".to_string()));
+            }
+            if self.pangea_username.is_none() || self.pangea_password.is_none() {
+                return Err(Error::Config(
+                    "Pangea credentials are not set, use PANGEA_USERNAME and PANGEA_PASSWORD"
+                        .to_string(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn network(&self) -> Network {
+        self.network.expect("network is validated")
+    }
+
+    /// Returns the url of the Fuel node.
+    pub fn fuel_url(&self) -> &str {
+        self.fuel_url
+            .as_deref()
+            .unwrap_or_else(|| self.network().fuel_url())
+    }
+
+    pub fn stall_timeout(&self) -> Duration {
+        Duration::from_secs(self.stall_timeout_secs)
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+
+    pub fn database_url(&self) -> &str {
+        self.database_url
+            .as_deref()
+            .expect("database url is validated")
+    }
+
+    /// Returns the block to start indexing a market from.
+    pub fn start_block(&self, market: &MarketInfo) -> i64 {
+        market.start_block.unwrap_or(self.pangea_start_block)
+    }
+
+    /// Returns the number of blocks fetched at once while catching up a market.
+    pub fn batch_size(&self, market: &MarketInfo) -> u64 {
+        market.batch_size.unwrap_or(self.batch_size)
+    }
+}
+
+/// Whether the id is a 32 bytes hex string.
+fn is_hex_id(id: &str) -> bool {
+    id.strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Markets to be indexed by an instance.
+///
+/// Every market of the config is indexed unless they are narrowed down by ids, and then by
+/// a shard. Markets are assigned to shards round-robin in the config order, so every instance
+/// has to run with the same config.
+#[derive(Debug, Default)]
+pub struct MarketSelection {
+    /// Market ids, empty to select all the markets
+    pub ids: Vec<String>,
+
+    /// Shard index and the number of shards
+    pub shard: Option<(usize, usize)>,
+}
+
+impl Config {
+    /// Keeps only the selected markets.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a selected id isn't a market of the config or the shard is out of range.
+    pub fn select_markets(&mut self, selection: &MarketSelection) -> Result<(), Error> {
+        if let Some(id) = selection.ids.iter().find(|id| {
+            !self
+                .markets
+                .iter()
+                .any(|market| market.id.eq_ignore_ascii_case(id))
+        }) {
+            return Err(Error::Config(format!("Unknown market {}", id)));
+        }
+
+        if !selection.ids.is_empty() {
+            self.markets.retain(|market| {
+                selection
+                    .ids
+                    .iter()
+                    .any(|id| market.id.eq_ignore_ascii_case(id))
+            });
+        }
+
+        if let Some((index, count)) = selection.shard {
+            if index >= count {
+                return Err(Error::Config(format!(
+                    "Shard index {} is out of {} shards",
+                    index, count
+                )));
+            }
+
+            self.markets = std::mem::take(&mut self.markets)
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| i % count == index)
+                .map(|(_, market)| market)
+                .collect();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn market_id(index: usize) -> String {
+        format!("0x{}{:04x}", "ab".repeat(30), index)
+    }
+
+    /// A config of the given number of markets, named by their index.
+    fn config(market_count: usize) -> Config {
+        let markets = (0..market_count)
+            .map(|i| json!({ "id": market_id(i), "name": i.to_string() }))
+            .collect::<Vec<_>>();
+
+        serde_json::from_value(json!({
+            "network": "mainnet",
+            "pangea_host": "pangea.test",
+            "pangea_start_block": 0,
+            "markets": markets,
+        }))
+        .unwrap()
+    }
+
+    fn names(config: &Config) -> Vec<&str> {
+        config
+            .markets
+            .iter()
+            .map(|market| market.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn maps_the_legacy_chain_ids_to_networks() {
+        assert_eq!(Network::from_chain_id("FUEL").unwrap(), Network::Mainnet);
+        assert_eq!(
+            Network::from_chain_id("FUELTESTNET").unwrap(),
+            Network::Testnet
+        );
+        for chain_id in ["", "fuel", "ETH"] {
+            assert!(matches!(
+                Network::from_chain_id(chain_id),
+                Err(Error::Config(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn validates_the_connections_apart() {
+        let mut config = config(1);
+        config.validate().unwrap();
+        assert!(matches!(
+            config.validate_connections(),
+            Err(Error::Config(_))
+        ));
+
+        config.database_url = Some("postgres://forge@localhost/forge".to_string());
+        config.replay_file = Some(PathBuf::from("events.jsonl"));
+        config.validate_connections().unwrap();
+    }
+
+    #[test]
+    fn selects_every_market_by_default() {
+        let mut config = config(3);
+        config.select_markets(&MarketSelection::default()).unwrap();
+
+        assert_eq!(names(&config), ["0", "1", "2"]);
+    }
+
+    #[test]
+    fn selects_the_markets_of_an_id_list() {
+        let mut config = config(4);
+        let selection = MarketSelection {
+            ids: vec![market_id(3), market_id(1).replace("ab", "AB")],
+            shard: None,
+        };
+        config.select_markets(&selection).unwrap();
+
+        // The config order is kept whatever the order and the case of the ids
+        assert_eq!(names(&config), ["1", "3"]);
+    }
+
+    #[test]
+    fn rejects_an_unknown_id() {
+        let mut config = config(2);
+        let selection = MarketSelection {
+            ids: vec![market_id(0), market_id(7)],
+            shard: None,
+        };
+
+        let result = config.select_markets(&selection);
+        assert!(matches!(result, Err(Error::Config(message)) if message.contains(&market_id(7))));
+    }
+
+    #[test]
+    fn partitions_the_markets_into_shards() {
+        let count = 3;
+        let shards = (0..count)
+            .map(|index| {
+                let mut config = config(7);
+                let selection = MarketSelection {
+                    ids: Vec::new(),
+                    shard: Some((index, count)),
+                };
+                config.select_markets(&selection).unwrap();
+                names(&config)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            shards,
+            [vec!["0", "3", "6"], vec!["1", "4"], vec!["2", "5"]]
+        );
+
+        // Every market is indexed by exactly one shard
+        let mut markets = shards.concat();
+        markets.sort();
+        assert_eq!(markets, ["0", "1", "2", "3", "4", "5", "6"]);
+    }
+
+    #[test]
+    fn shards_the_selected_ids() {
+        let mut config = config(5);
+        let selection = MarketSelection {
+            ids: vec![market_id(1), market_id(2), market_id(4)],
+            shard: Some((1, 2)),
+        };
+        config.select_markets(&selection).unwrap();
+
+        assert_eq!(names(&config), ["2"]);
+    }
+
+    #[test]
+    fn rejects_a_shard_out_of_range() {
+        for shard in [(2, 2), (3, 2), (0, 0)] {
+            let mut config = config(2);
+            let selection = MarketSelection {
+                ids: Vec::new(),
+                shard: Some(shard),
+            };
+
+            let result = config.select_markets(&selection);
+            assert!(matches!(result, Err(Error::Config(_))), "shard {:?}", shard);
+        }
+    }
 }
@@ -1,11 +1,17 @@
-use sea_orm::{Database, DatabaseConnection};
-use std::env;
-
 use crate::error::Error;
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
-pub async fn build_connection() -> Result<DatabaseConnection, Error> {
-    let database_url = env::var("DATABASE_URL").unwrap();
+pub async fn build_connection(database_url: &str) -> Result<DatabaseConnection, Error> {
     let db_conn: DatabaseConnection = Database::connect(database_url).await?;
 
     Ok(db_conn)
 }
+
+/// Builds a connection of its own, which isn't shared with the rest of the database pool.
+pub async fn build_dedicated_connection(database_url: &str) -> Result<DatabaseConnection, Error> {
+    let mut options = ConnectOptions::new(database_url);
+    options.max_connections(1).min_connections(1);
+    let db_conn: DatabaseConnection = Database::connect(options).await?;
+
+    Ok(db_conn)
+}
@@ -1,53 +1,185 @@
-use sea_orm::DatabaseConnection;
-use sparker_core::{repo, LimitType, Order, OrderStatus, Trade, UpdateOrder};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use chrono::NaiveDateTime;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, TransactionTrait};
+use sparker_core::{
+    repo, LimitType, NewDeadLetter, Order, OrderEvent, OrderEventType, OrderStatus, Trade,
+    UpdateOrder, U128,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+};
+use tokio::{
+    sync::{mpsc, Mutex},
+    time::{sleep, Duration, Instant},
+};
 
-use crate::types::Receiver;
+use crate::{
+    error::Error,
+    metrics::METRICS,
+    pangea::event::{PangeaEvent, INVALID_EVENT},
+    types::{Cancellation, Position, Receiver, Rejection, Sender},
+};
 
+const MIN_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(32);
+/// Number of blocks behind the latest processed block for which order changes can be reverted.
+const UNDO_LOG_DEPTH: i64 = 10_000;
+/// Number of operations which can be queued for a dispatcher. Once it's full, the indexer waits
+/// for the dispatcher, which slows down the fetching of events.
+const OPERATION_CHANNEL_CAPACITY: usize = 10_000;
+
+#[allow(clippy::large_enum_variant)]
 pub enum Operation {
     Update(Update),
+    /// Block number and hash of the event which precedes the next updates.
+    Block(i64, String),
+    /// Block number to be stored as the latest processed block, every event of the blocks
+    /// before it was received.
     Dispatch(i64),
+    /// First block which is no longer on the canonical chain.
+    Rollback(i64),
     Prune(i64),
+    /// An event which couldn't be turned into an update.
+    DeadLetter(Rejection),
+    /// An event to be appended to the journal as it was received.
+    Journal(Arc<PangeaEvent>),
+    /// Applies the queued updates of redriven dead letters, without moving the latest
+    /// processed block.
+    Redrive(Vec<i32>),
 }
 
-pub enum Update {
+/// Creates the bounded channel of the operations of a dispatcher.
+pub fn operation_channel() -> (Sender<Operation>, Receiver<Operation>) {
+    let (operation_tx, operation_rx) = mpsc::channel(OPERATION_CHANNEL_CAPACITY);
+
+    (Arc::new(operation_tx), Arc::new(Mutex::new(operation_rx)))
+}
+
+pub struct Update {
+    /// Position of the event which produced the update.
+    pub position: Position,
+    pub kind: UpdateKind,
+    /// The event which produced the update, dead-lettered if the update can't be applied.
+    pub event: Arc<PangeaEvent>,
+}
+
+pub enum UpdateKind {
     OpenOrder(sparker_core::Order),
     Trade(sparker_core::Trade),
-    CancelOrder(String),
+    CancelOrder(Cancellation),
+}
+
+impl UpdateKind {
+    fn is_same_kind(&self, other: &UpdateKind) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+/// An IOC, FOK or MKT order which was partially filled by a trade.
+///
+/// Such orders can't stay on the book, their remainder is settled once the transaction which
+/// filled them is over.
+#[derive(Clone)]
+struct Settlement {
+    /// Position of the trade which filled the order.
+    position: Position,
+    order_id: String,
+    limit_type: LimitType,
+    tx_id: String,
+    timestamp: NaiveDateTime,
+}
+
+impl Settlement {
+    /// Whether the transaction of the settlement is over at the given position.
+    fn is_due(&self, position: Position) -> bool {
+        (self.position.block_number, self.position.transaction_index)
+            < (position.block_number, position.transaction_index)
+    }
 }
 
 pub struct OperationDispatcher {
     market_id: String,
+    /// Name of the market used to label its metrics.
+    market_name: String,
     db_conn: Arc<DatabaseConnection>,
     updates: Mutex<Vec<Update>>,
+    blocks: Mutex<Vec<(i64, String)>>,
+    /// The highest block which hash is stored in the database.
+    stored_block: Mutex<Option<i64>>,
+    /// Partially filled orders waiting for the end of their transaction.
+    settlements: Mutex<Vec<Settlement>>,
+    rejections: Mutex<Vec<Rejection>>,
+    /// Whether the events are journaled, which is off when they are read from the journal.
+    journaling: bool,
+    journal: Mutex<Vec<Arc<PangeaEvent>>>,
     operation_rx: Receiver<Operation>,
 }
 
 impl OperationDispatcher {
     pub fn new(
         market_id: String,
+        market_name: String,
         db_conn: Arc<DatabaseConnection>,
         operation_rx: Receiver<Operation>,
+        journaling: bool,
     ) -> Self {
         Self {
             market_id,
+            market_name,
             db_conn,
             updates: Mutex::new(Vec::new()),
+            blocks: Mutex::new(Vec::new()),
+            stored_block: Mutex::new(None),
+            settlements: Mutex::new(Vec::new()),
+            rejections: Mutex::new(Vec::new()),
+            journaling,
+            journal: Mutex::new(Vec::new()),
             operation_rx,
         }
     }
 
+    /// Processes the operations until their channel is closed.
     pub async fn start(&self) {
-        while let Some(operation) = self.operation_rx.lock().await.recv().await {
+        while let Some(operation) = self.next_operation().await {
+            METRICS.start_operation(&self.market_name);
             match operation {
                 Operation::Update(update) => self.update(update).await,
-                Operation::Dispatch(block) => self.dispatch(block).await,
+                Operation::Block(number, hash) => self.block(number, hash).await,
+                Operation::Dispatch(block) => self.dispatch(Some(block), Vec::new()).await,
+                Operation::Rollback(from_block) => self.rollback(from_block).await,
                 Operation::Prune(from_block) => self.prune(from_block).await,
+                Operation::DeadLetter(rejection) => self.reject(rejection).await,
+                Operation::Journal(event) => self.journal(event).await,
+                Operation::Redrive(ids) => self.dispatch(None, ids).await,
             }
+            METRICS.finish_operation(&self.market_name);
+        }
+
+        // The channel is closed, the updates which weren't dispatched belong to a block which
+        // wasn't received completely and is fetched again on restart
+        let updates = self.updates.lock().await;
+        if !updates.is_empty() {
+            log::info!(
+                "[{}] UPDATES_DISCARDED: {} of an incomplete block",
+                self.market_id,
+                updates.len()
+            );
         }
     }
 
+    /// Receives the next operation and records the number of operations left in the channel.
+    async fn next_operation(&self) -> Option<Operation> {
+        let mut operation_rx = self.operation_rx.lock().await;
+        let operation = operation_rx.recv().await;
+        METRICS
+            .queue_depth
+            .with_label_values(&[&self.market_name])
+            .set(operation_rx.len() as i64);
+
+        operation
+    }
+
     /// Adds an update to the queue.
     ///
     /// # Arguments
@@ -58,180 +190,942 @@ impl OperationDispatcher {
         updates.push(update);
     }
 
-    /// Dispatches the updates for a given block.
+    /// Adds a rejected event to the queue, it's stored as a dead letter on dispatch.
     ///
-    /// This method processes the updates by extracting open orders, cancel orders, and trades,
-    /// and then processes them in the following order:
-    /// 1. Open orders
-    /// 2. Trade and update orders
-    /// 3. Cancel orders
+    /// # Arguments
     ///
-    /// After processing, it clears the updates and updates the latest processed block in the database.
+    /// * `rejection` - The rejected event.
+    pub async fn reject(&self, rejection: Rejection) {
+        let mut rejections = self.rejections.lock().await;
+        rejections.push(rejection);
+    }
+
+    /// Adds an event to the journal queue, it's appended to the journal on dispatch.
     ///
     /// # Arguments
     ///
-    /// * `block` - The block number to be processed.
+    /// * `event` - The event as it was received.
+    pub async fn journal(&self, event: Arc<PangeaEvent>) {
+        if self.journaling {
+            let mut journal = self.journal.lock().await;
+            journal.push(event);
+        }
+    }
+
+    /// Verifies a block against the stored chain and queues its hash.
+    ///
+    /// A block which is already stored but comes with a different hash means the chain was
+    /// reorganized. In this case everything from the block onwards is rolled back before the
+    /// updates of the canonical chain are applied.
     ///
-    pub async fn dispatch(&self, block: i64) {
+    /// # Arguments
+    ///
+    /// * `number` - The block number.
+    /// * `hash` - The block hash.
+    ///
+    pub async fn block(&self, number: i64, hash: String) {
+        if self
+            .blocks
+            .lock()
+            .await
+            .last()
+            .is_some_and(|(last, _)| *last == number)
+        {
+            return;
+        }
+
+        let stored_block = *self.stored_block.lock().await;
+        if stored_block.is_some_and(|stored_block| number <= stored_block) {
+            let stored_hash = self
+                .retry("FIND_BLOCK_HASH", || async {
+                    let stored_hash = repo::state::Query::find_block_hash(
+                        self.db_conn.as_ref(),
+                        &self.market_id,
+                        number,
+                    )
+                    .await?;
+
+                    Ok(stored_hash)
+                })
+                .await;
+
+            if let Some(stored_hash) = stored_hash.filter(|stored_hash| *stored_hash != hash) {
+                log::warn!(
+                    "REORG_DETECTED: block {} hash {} (stored {})",
+                    number,
+                    hash,
+                    stored_hash
+                );
+                self.rollback(number).await;
+            }
+        }
+
+        self.blocks.lock().await.push((number, hash));
+    }
+
+    /// Dispatches the queued updates.
+    ///
+    /// This method applies the updates in their on-chain order, i.e. by block number,
+    /// transaction index and log index. Consecutive updates of the same kind are processed
+    /// as a single batch:
+    /// - Open orders are inserted at once
+    /// - Trades update their orders and are inserted at once
+    /// - Cancel orders update the status of their orders
+    ///
+    /// Before each batch, the IOC, FOK and MKT orders filled by earlier transactions are settled.
+    /// Once the updates are applied, the orders filled by the blocks before the checkpoint are
+    /// settled as well, since their transactions are over. The settlements of the checkpoint
+    /// block are kept until a later update or checkpoint arrives.
+    ///
+    /// The updates, the journaled events, the dead letters, the hashes of the processed blocks and
+    /// the latest processed block are written in a single transaction, so the block is either fully
+    /// applied or not at all.
+    /// A failed transaction is retried until it succeeds, the updates are cleared only after that.
+    ///
+    /// # Arguments
+    ///
+    /// * `checkpoint` - The block number to be stored as the latest processed block, `None` to
+    ///   leave it untouched when redriving.
+    /// * `redriven` - The IDs of the dead letters whose events are among the updates.
+    ///
+    pub async fn dispatch(&self, checkpoint: Option<i64>, redriven: Vec<i32>) {
+        let started_at = Instant::now();
         let mut updates = self.updates.lock().await;
-        let open_orders = extract_updates(&updates, |update| {
-            if let Update::OpenOrder(data) = update {
-                Some(data.clone())
-            } else {
-                None
+        let mut blocks = self.blocks.lock().await;
+        let mut rejections = self.rejections.lock().await;
+        let mut journal = self.journal.lock().await;
+
+        // Sorting is stable, so updates of the same event keep their order
+        updates.sort_by_key(|update| update.position);
+
+        let mut settlements = self.settlements.lock().await;
+        *settlements = self
+            .retry("DISPATCH", || {
+                self.apply(
+                    &updates,
+                    &blocks,
+                    &rejections,
+                    &journal,
+                    &settlements,
+                    checkpoint,
+                    &redriven,
+                )
+            })
+            .await;
+
+        if let Some((last_block, _)) = blocks.last() {
+            let mut stored_block = self.stored_block.lock().await;
+            *stored_block = Some(stored_block.map_or(*last_block, |b| b.max(*last_block)));
+        }
+
+        self.record_metrics(&updates, &rejections, &journal, checkpoint);
+        METRICS
+            .dispatch_duration
+            .with_label_values(&[&self.market_name])
+            .observe(started_at.elapsed().as_secs_f64());
+
+        // Clear operations after dispatch
+        updates.clear();
+        blocks.clear();
+        rejections.clear();
+        journal.clear();
+    }
+
+    /// Records the metrics of a completed dispatch.
+    ///
+    /// # Arguments
+    ///
+    /// * `updates` - The applied updates.
+    /// * `rejections` - The events stored as dead letters.
+    /// * `journal` - The journaled events, empty if the events aren't journaled.
+    /// * `checkpoint` - The block number stored as the latest processed block, if any.
+    ///
+    fn record_metrics(
+        &self,
+        updates: &[Update],
+        rejections: &[Rejection],
+        journal: &[Arc<PangeaEvent>],
+        checkpoint: Option<i64>,
+    ) {
+        for update in updates {
+            let event_type = update.event.event_type.as_deref().unwrap_or_default();
+            METRICS
+                .events
+                .with_label_values(&[&self.market_name, event_type])
+                .inc();
+        }
+        if !rejections.is_empty() {
+            METRICS
+                .events
+                .with_label_values(&[&self.market_name, "Rejected"])
+                .inc_by(rejections.len() as u64);
+        }
+
+        let last_event_timestamp = updates
+            .iter()
+            .map(|update| update.event.as_ref())
+            .chain(journal.iter().map(Arc::as_ref))
+            .map(|event| event.block_timestamp)
+            .max();
+        if let Some(timestamp) = last_event_timestamp {
+            METRICS.set_last_event_timestamp(&self.market_name, timestamp);
+        }
+        if let Some(block) = checkpoint {
+            METRICS.set_latest_processed_block(&self.market_name, block);
+        }
+    }
+
+    /// Applies the updates and moves the latest processed block within a transaction.
+    ///
+    /// The events are journaled within the same transaction, so the journal never holds an
+    /// event whose block wasn't applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `updates` - The updates to be applied, sorted by their position.
+    /// * `blocks` - The numbers and hashes of the blocks the updates belong to.
+    /// * `rejections` - The rejected events to be stored as dead letters.
+    /// * `journal` - The events to be appended to the journal.
+    /// * `settlements` - The settlements pending from the previous dispatch.
+    /// * `checkpoint` - The block number to be stored as the latest processed block, if any.
+    /// * `redriven` - The IDs of the dead letters to be marked as redriven.
+    ///
+    /// # Returns
+    ///
+    /// Returns the settlements which are still pending.
+    ///
+    #[allow(clippy::too_many_arguments)]
+    async fn apply(
+        &self,
+        updates: &[Update],
+        blocks: &[(i64, String)],
+        rejections: &[Rejection],
+        journal: &[Arc<PangeaEvent>],
+        settlements: &[Settlement],
+        checkpoint: Option<i64>,
+        redriven: &[i32],
+    ) -> Result<Vec<Settlement>, Error> {
+        let txn = self.db_conn.begin().await?;
+        let mut settlements = settlements.to_vec();
+
+        let raw_events = journal
+            .iter()
+            .map(|event| event.build_raw_event())
+            .collect();
+        repo::raw_event::Mutation::insert_many(&txn, raw_events).await?;
+
+        for batch in updates.chunk_by(|a, b| a.kind.is_same_kind(&b.kind)) {
+            settlements = self
+                .process_settlements(&txn, settlements, batch[0].position)
+                .await?;
+
+            match batch[0].kind {
+                UpdateKind::OpenOrder(_) => {
+                    let opens = extract_updates(batch, |update| {
+                        if let UpdateKind::OpenOrder(data) = &update.kind {
+                            Some((Arc::clone(&update.event), data.clone()))
+                        } else {
+                            None
+                        }
+                    });
+                    self.process_open_orders(&txn, opens).await?;
+                }
+                UpdateKind::Trade(_) => {
+                    let trades = extract_updates(batch, |update| {
+                        if let UpdateKind::Trade(data) = &update.kind {
+                            Some((Arc::clone(&update.event), data.clone()))
+                        } else {
+                            None
+                        }
+                    });
+                    let new_settlements = self.process_trades(&txn, trades).await?;
+
+                    settlements.retain(|settlement| {
+                        !new_settlements
+                            .iter()
+                            .any(|new| new.order_id == settlement.order_id)
+                    });
+                    settlements.extend(new_settlements);
+                }
+                UpdateKind::CancelOrder(_) => {
+                    let cancels = extract_updates(batch, |update| {
+                        if let UpdateKind::CancelOrder(data) = &update.kind {
+                            Some((Arc::clone(&update.event), data.clone()))
+                        } else {
+                            None
+                        }
+                    });
+                    self.process_cancel_orders(&txn, cancels).await?;
+                }
             }
-        });
-        let cancel_order_ids = extract_updates(&updates, |update| {
-            if let Update::CancelOrder(data) = update {
-                Some(data.clone())
-            } else {
-                None
+        }
+
+        let dead_letters = rejections
+            .iter()
+            .map(|rejection| self.dead_letter(rejection.clone()))
+            .collect();
+        repo::dead_letter::Mutation::insert_many(&txn, dead_letters).await?;
+        repo::state::Mutation::upsert_block_hashes(&txn, &self.market_id, blocks.to_vec()).await?;
+
+        match checkpoint {
+            Some(block) => {
+                let checkpoint_block = Position {
+                    block_number: block,
+                    transaction_index: 0,
+                    log_index: 0,
+                };
+                settlements = self
+                    .process_settlements(&txn, settlements, checkpoint_block)
+                    .await?;
+
+                repo::state::Mutation::upsert_latest_processed_block(&txn, block, &self.market_id)
+                    .await?;
+                repo::order_undo::Mutation::delete_before(
+                    &txn,
+                    &self.market_id,
+                    block - UNDO_LOG_DEPTH,
+                )
+                .await?;
             }
-        });
-        let trades = extract_updates(&updates, |update| {
-            if let Update::Trade(data) = update {
-                Some(data.clone())
-            } else {
-                None
+            None => {
+                // Nothing follows the redriven events, so their transactions are over
+                settlements = self
+                    .process_settlements(&txn, settlements, Position::MAX)
+                    .await?;
+                repo::dead_letter::Mutation::mark_redriven(&txn, redriven.to_vec()).await?;
             }
+        }
+
+        txn.commit().await?;
+
+        Ok(settlements)
+    }
+
+    /// Rolls the market back to the block preceding the fork point of a reorganization.
+    ///
+    /// The queued operations of the orphaned blocks are discarded along with everything which
+    /// was stored from them, the events of the canonical chain are applied from scratch.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_block` - The first block which is no longer on the canonical chain.
+    ///
+    async fn rollback(&self, from_block: i64) {
+        self.updates
+            .lock()
+            .await
+            .retain(|update| update.position.block_number < from_block);
+        self.blocks
+            .lock()
+            .await
+            .retain(|(block, _)| *block < from_block);
+        self.rejections.lock().await.retain(|rejection| {
+            rejection
+                .block_number
+                .is_none_or(|block_number| block_number < from_block)
         });
+        self.journal
+            .lock()
+            .await
+            .retain(|event| event.block_number < from_block);
 
-        self.process_open_orders(open_orders).await;
-        self.process_trades(trades).await;
-        self.process_cancel_orders(cancel_order_ids).await;
+        self.retry("ROLLBACK", || async {
+            let txn = self.db_conn.begin().await?;
 
-        // Clear operations after dispatch
-        updates.clear();
+            self.delete_from(&txn, from_block).await?;
+            repo::state::Mutation::upsert_latest_processed_block(
+                &txn,
+                from_block - 1,
+                &self.market_id,
+            )
+            .await?;
 
-        if let Err(e) = repo::state::Mutation::upsert_latest_processed_block(
-            &self.db_conn,
-            block,
-            &self.market_id,
-        )
-        .await
-        {
-            log::error!("UPSERT_LATEST_PROCESSED_BLOCK_ERROR: {}", e);
-        }
+            txn.commit().await?;
+
+            Ok(())
+        })
+        .await;
+
+        *self.stored_block.lock().await = Some(from_block - 1);
+        self.discard_settlements(from_block).await;
     }
 
-    /// Prunes the orders and trades from the database.
+    /// Prunes the orders, trades and block hashes from the database.
+    ///
+    /// Orders which were changed by the pruned blocks are reverted to their previous state. The
+    /// pruned blocks are fetched again, so their journaled events are pruned as well.
     ///
     /// # Arguments
     ///
     /// * `from_block` - The block number to start pruning from.
     ///
     async fn prune(&self, from_block: i64) {
-        if let Err(e) = repo::trade::Mutation::delete_many(&self.db_conn, self.market_id.clone(), from_block)
+        self.retry("PRUNE", || async {
+            let txn = self.db_conn.begin().await?;
+            self.delete_from(&txn, from_block).await?;
+            txn.commit().await?;
+
+            Ok(())
+        })
+        .await;
+
+        *self.stored_block.lock().await = Some(from_block - 1);
+        self.discard_settlements(from_block).await;
+    }
+
+    /// Discards the pending settlements of the trades which were deleted.
+    async fn discard_settlements(&self, from_block: i64) {
+        self.settlements
+            .lock()
             .await
-        {
-            log::error!("PRUNE_TRADES_ERROR: {}", e);
+            .retain(|settlement| settlement.position.block_number < from_block);
+    }
+
+    /// Turns a rejected event into a dead letter of the market.
+    fn dead_letter(&self, rejection: Rejection) -> NewDeadLetter {
+        NewDeadLetter {
+            market_id: self.market_id.clone(),
+            block_number: rejection.block_number.map(|block| block as u64),
+            payload: rejection.payload,
+            reason: rejection.reason,
         }
+    }
 
-        if let Err(e) = repo::order::Mutation::delete_many(&self.db_conn, self.market_id.clone(), from_block)
-            .await
-        {
-            log::error!("PRUNE_ORDERS_ERROR: {}", e);
+    /// Quarantines the event of a row which violates a constraint as a dead letter, the other
+    /// rows of its batch are still written.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event which produced the row.
+    /// * `id` - The ID of the row used in the logs.
+    /// * `reason` - The constraint the row violates.
+    ///
+    fn quarantine(&self, event: &PangeaEvent, id: &str, reason: &str) -> NewDeadLetter {
+        log::error!("[{}] ROW_QUARANTINED: {}: {}", self.market_id, id, reason);
+        self.dead_letter(event.reject(format!("ROW_QUARANTINED: {}", reason)))
+    }
+
+    /// Runs a database operation until it succeeds, backing off exponentially between attempts.
+    ///
+    /// While the operation keeps failing the dispatcher is stalled: no further operations are
+    /// processed, so the latest processed block isn't advanced past the failed one.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the operation used in the logs.
+    /// * `operation` - The operation to be run.
+    ///
+    async fn retry<T, F, Fut>(&self, name: &str, operation: F) -> T
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut backoff = MIN_RETRY_BACKOFF;
+        let mut attempt = 1;
+
+        loop {
+            match operation().await {
+                Ok(value) => {
+                    if attempt > 1 {
+                        log::info!(
+                            "[{}] {}_RECOVERED: after {} attempts",
+                            self.market_id,
+                            name,
+                            attempt
+                        );
+                    }
+
+                    return value;
+                }
+                Err(e) => {
+                    METRICS
+                        .db_errors
+                        .with_label_values(&[&self.market_name, name])
+                        .inc();
+                    log::error!(
+                        "[{}] {}_STALLED: attempt {}, retrying in {:?}: {}",
+                        self.market_id,
+                        name,
+                        attempt,
+                        backoff,
+                        e
+                    );
+                }
+            }
+
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+            attempt += 1;
         }
     }
 
+    /// Deletes the trades, orders, order events, pending dead letters and block hashes starting
+    /// from the given block, along with the journaled events unless they are being replayed.
+    ///
+    /// Events which couldn't be decoded aren't journaled, so their dead letters are kept when
+    /// the events are replayed.
+    ///
+    /// The orders changed starting from the block are reverted first, so the orders which
+    /// were opened before it survive with the state they had at that time.
+    async fn delete_from<C: ConnectionTrait>(
+        &self,
+        db_conn: &C,
+        from_block: i64,
+    ) -> Result<(), DbErr> {
+        let reverted =
+            repo::order_undo::Mutation::revert(db_conn, &self.market_id, from_block).await?;
+        if reverted > 0 {
+            log::info!(
+                "[{}] ORDERS_REVERTED: {} from block {}",
+                self.market_id,
+                reverted,
+                from_block
+            );
+        }
+
+        repo::trade::Mutation::delete_many(db_conn, self.market_id.clone(), from_block).await?;
+        repo::order_event::Mutation::delete_many(db_conn, self.market_id.clone(), from_block)
+            .await?;
+        repo::order::Mutation::delete_many(db_conn, self.market_id.clone(), from_block).await?;
+        let kept_reason = (!self.journaling).then_some(INVALID_EVENT);
+        repo::dead_letter::Mutation::delete_many(db_conn, &self.market_id, from_block, kept_reason)
+            .await?;
+        repo::state::Mutation::delete_block_hashes(db_conn, &self.market_id, from_block).await?;
+        if self.journaling {
+            repo::raw_event::Mutation::delete_many(db_conn, &self.market_id, from_block).await?;
+        }
+
+        Ok(())
+    }
+
     /// Processes the opening of orders by inserting them into the database.
     ///
     /// This method takes a vector of orders and attempts to insert them into the database.
+    /// An order which was cancelled before is opened again, its cancelled state is recorded
+    /// so it can be reverted. An `Open` event is recorded for every order which was opened.
+    /// The event of an order which can't be inserted is quarantined as a dead letter.
+    ///
+    /// A row can't be upserted twice by the same statement, so an order opened several times
+    /// in the batch is inserted once with its last opening.
     ///
     /// # Arguments
     ///
-    /// * `orders` - A vector of orders to be inserted into the database.
+    /// * `db_conn` - The connection (or transaction) to write with.
+    /// * `opens` - A vector of orders to be inserted into the database along with their events.
     ///
-    async fn process_open_orders(&self, orders: Vec<Order>) {
-        if let Err(e) = repo::order::Mutation::insert_many(&self.db_conn, orders).await {
-            log::error!("CREATE_ORDERS_ERROR: {}", e);
+    async fn process_open_orders<C: ConnectionTrait + TransactionTrait>(
+        &self,
+        db_conn: &C,
+        opens: Vec<(Arc<PangeaEvent>, Order)>,
+    ) -> Result<(), DbErr> {
+        let last_indexes = opens
+            .iter()
+            .enumerate()
+            .map(|(index, (_, order))| (order.order_id.clone(), index))
+            .collect::<HashMap<String, usize>>();
+        let opens = opens
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, order))| last_indexes[&order.order_id] == *index)
+            .map(|(_, open)| open)
+            .collect::<Vec<_>>();
+
+        let order_ids = opens
+            .iter()
+            .map(|(_, order)| order.order_id.clone())
+            .collect();
+        let existing = repo::order::Query::find_by_ids(db_conn, order_ids).await?;
+
+        let orders = opens.iter().map(|(_, order)| order.clone()).collect();
+        let skipped = repo::order::Mutation::insert_many_isolating(db_conn, orders).await?;
+
+        let mut dead_letters = Vec::new();
+        let mut skipped_indexes = HashSet::new();
+        for row in skipped {
+            let (event, _) = &opens[row.index];
+            dead_letters.push(self.quarantine(event, &row.row.order_id, &row.reason));
+            skipped_indexes.insert(row.index);
+        }
+
+        let mut undos = Vec::new();
+        let mut events = Vec::new();
+        for (index, (_, order)) in opens.iter().enumerate() {
+            if skipped_indexes.contains(&index) {
+                continue;
+            }
+
+            match existing.iter().find(|prev| prev.order_id == order.order_id) {
+                Some(prev) if prev.status == OrderStatus::Cancelled => {
+                    undos.push((order.block_number as i64, prev.clone()));
+                }
+                // Orders are opened only once unless they were cancelled
+                Some(_) => continue,
+                None => {}
+            }
+
+            events.push(OrderEvent {
+                order_id: order.order_id.clone(),
+                event_type: OrderEventType::Open,
+                tx_id: order.tx_id.clone(),
+                block_number: order.block_number,
+                timestamp: order.timestamp,
+                amount_before: U128::ZERO,
+                amount_after: order.remaining_amount,
+                status: order.status,
+                market_id: order.market_id.clone(),
+            });
         }
+
+        repo::order_undo::Mutation::insert_many(db_conn, &self.market_id, undos).await?;
+        repo::order_event::Mutation::insert_many(db_conn, events).await?;
+        repo::dead_letter::Mutation::insert_many(db_conn, dead_letters).await
     }
 
     /// Processes the cancellation of orders by updating their status to `Cancelled` in the database.
     ///
-    /// For each order ID in the provided vector, it attempts to update the order's status to `Cancelled`.
-    /// If an error occurs during the update, it logs the error.
+    /// For each order ID in the provided vector, it attempts to update the order's status to `Cancelled`
+    /// and records the previous state of the order along with a `Cancel` event. The event of a missing
+    /// order is dead-lettered.
     ///
     /// # Arguments
     ///
-    /// * `order_ids` - A vector of order IDs to be cancelled.
-    ///
-    async fn process_cancel_orders(&self, order_ids: Vec<String>) {
-        for order_id in order_ids {
-            if let Err(e) = repo::order::Mutation::update(
-                &self.db_conn,
-                UpdateOrder {
-                    order_id,
-                    amount: None,
-                    status: OrderStatus::Cancelled,
-                },
-            )
-            .await
-            {
-                log::error!("CANCEL_ORDER_ERROR: {}", e);
+    /// * `db_conn` - The connection (or transaction) to write with.
+    /// * `cancels` - A vector of cancellations of orders along with their events.
+    ///
+    async fn process_cancel_orders<C: ConnectionTrait>(
+        &self,
+        db_conn: &C,
+        cancels: Vec<(Arc<PangeaEvent>, Cancellation)>,
+    ) -> Result<(), DbErr> {
+        let mut undos = Vec::new();
+        let mut events = Vec::new();
+        let mut dead_letters = Vec::new();
+
+        for (event, cancel) in cancels {
+            match repo::order::Query::find_by_id(db_conn, &cancel.order_id).await? {
+                Some(order) => {
+                    repo::order::Mutation::update(
+                        db_conn,
+                        UpdateOrder {
+                            order_id: cancel.order_id.clone(),
+                            filled_amount: None,
+                            remaining_amount: None,
+                            average_fill_price: None,
+                            fill_volume: None,
+                            status: OrderStatus::Cancelled,
+                        },
+                    )
+                    .await?;
+
+                    events.push(OrderEvent {
+                        order_id: cancel.order_id,
+                        event_type: OrderEventType::Cancel,
+                        tx_id: cancel.tx_id,
+                        block_number: cancel.block_number as u64,
+                        timestamp: cancel.timestamp,
+                        amount_before: order.remaining_amount,
+                        amount_after: order.remaining_amount,
+                        status: OrderStatus::Cancelled,
+                        market_id: order.market_id.clone(),
+                    });
+                    undos.push((cancel.block_number, order));
+                }
+                None => {
+                    log::error!("CANCEL_ORDER_ERROR: Missing order {}", cancel.order_id);
+                    let rejection = event.reject(format!(
+                        "CANCEL_ORDER_ERROR: Missing order {}",
+                        cancel.order_id
+                    ));
+                    dead_letters.push(self.dead_letter(rejection));
+                }
             }
         }
+
+        repo::order_undo::Mutation::insert_many(db_conn, &self.market_id, undos).await?;
+        repo::order_event::Mutation::insert_many(db_conn, events).await?;
+        repo::dead_letter::Mutation::insert_many(db_conn, dead_letters).await
     }
 
     /// Processes the given trades by updating the corresponding orders and inserting the trades into the database.
     ///
-    /// For each trade, it finds the corresponding order by its ID. If the order is found, it updates the order's status
-    /// and amount based on the trade's limit type. If the order is not found, it logs an error.
+    /// The orders of the trades are loaded at once and the fills are applied to them in memory, see [`apply_fills`].
+    /// The matched trades are inserted first, a trade which can't be inserted is quarantined as a dead letter and
+    /// the fills are applied again without it. The resulting states of the orders are then written back at once,
+    /// along with the recorded previous states, the events and the dead letters of the trades whose order is not found.
     ///
-    /// After processing all trades, it inserts the trades into the database.
+    /// # Arguments
+    ///
+    /// * `db_conn` - The connection (or transaction) to write with.
+    /// * `trades` - A vector of trades to be processed along with their events.
+    ///
+    /// # Returns
+    ///
+    /// Returns the settlements of the partially filled IOC, FOK and MKT orders.
+    ///
+    async fn process_trades<C: ConnectionTrait + TransactionTrait>(
+        &self,
+        db_conn: &C,
+        trades: Vec<(Arc<PangeaEvent>, Trade)>,
+    ) -> Result<Vec<Settlement>, DbErr> {
+        let order_ids = trades
+            .iter()
+            .map(|(_, trade)| trade.order_id.clone())
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+        let orders = repo::order::Query::find_by_ids(db_conn, order_ids)
+            .await?
+            .into_iter()
+            .map(|order| (order.order_id.clone(), order))
+            .collect::<HashMap<String, Order>>();
+
+        let mut fills = apply_fills(orders.clone(), trades.clone());
+        let matched_trades = fills
+            .trades
+            .iter()
+            .map(|(_, trade)| trade.clone())
+            .collect();
+        let skipped = repo::trade::Mutation::insert_many_isolating(db_conn, matched_trades).await?;
+
+        let mut dead_letters = Vec::new();
+        if !skipped.is_empty() {
+            let mut skipped_ids = HashSet::new();
+            for row in skipped {
+                let (event, _) = &fills.trades[row.index];
+                dead_letters.push(self.quarantine(event, &row.row.trade_id, &row.reason));
+                skipped_ids.insert(row.row.trade_id);
+            }
+
+            // The fills of the quarantined trades are left out by applying the batch again
+            // without them
+            let trades = trades
+                .into_iter()
+                .filter(|(_, trade)| !skipped_ids.contains(&trade.trade_id))
+                .collect();
+            fills = apply_fills(orders, trades);
+        }
+        dead_letters.extend(
+            fills
+                .rejections
+                .into_iter()
+                .map(|rejection| self.dead_letter(rejection)),
+        );
+
+        repo::order::Mutation::update_fills(db_conn, fills.orders).await?;
+        repo::order_undo::Mutation::insert_many(db_conn, &self.market_id, fills.undos).await?;
+        repo::order_event::Mutation::insert_many(db_conn, fills.events).await?;
+        repo::dead_letter::Mutation::insert_many(db_conn, dead_letters).await?;
+
+        Ok(fills.settlements)
+    }
+
+    /// Settles the remainders of the IOC, FOK and MKT orders whose transactions are over.
+    ///
+    /// The remainder of an IOC or MKT order expires, the order is `Cancelled` with the filled
+    /// amount kept. A FOK order has to be filled completely within its transaction, otherwise
+    /// it's marked as `Failed`. The orders are loaded and written back at once.
     ///
     /// # Arguments
     ///
-    /// * `trades` - A vector of trades to be processed.
+    /// * `db_conn` - The connection (or transaction) to write with.
+    /// * `settlements` - The pending settlements.
+    /// * `position` - The position of the next update to be applied.
     ///
-    async fn process_trades(&self, trades: Vec<Trade>) {
-        for trade in trades.iter() {
-            let order = match repo::order::Query::find_by_id(&self.db_conn, &trade.order_id).await {
-                Ok(order) => order,
-                Err(e) => {
-                    log::error!("FIND_ORDER_BY_ID_ERROR: {}", e);
-                    continue;
+    /// # Returns
+    ///
+    /// Returns the settlements which are not due yet.
+    ///
+    async fn process_settlements<C: ConnectionTrait>(
+        &self,
+        db_conn: &C,
+        settlements: Vec<Settlement>,
+        position: Position,
+    ) -> Result<Vec<Settlement>, DbErr> {
+        let (due, pending): (Vec<Settlement>, Vec<Settlement>) = settlements
+            .into_iter()
+            .partition(|settlement| settlement.is_due(position));
+        if due.is_empty() {
+            return Ok(pending);
+        }
+
+        let order_ids = due
+            .iter()
+            .map(|settlement| settlement.order_id.clone())
+            .collect();
+        let mut orders = repo::order::Query::find_by_ids(db_conn, order_ids)
+            .await?
+            .into_iter()
+            .map(|order| (order.order_id.clone(), order))
+            .collect::<HashMap<String, Order>>();
+
+        let mut settled = Vec::new();
+        let mut undos = Vec::new();
+        let mut events = Vec::new();
+
+        for settlement in due {
+            // An order is settled once, even if it has several settlements
+            let order = match orders.remove(&settlement.order_id) {
+                Some(order) if order.is_active() && !order.remaining_amount.is_zero() => order,
+                _ => continue,
+            };
+
+            let (status, event_type) = match settlement.limit_type {
+                LimitType::FOK => {
+                    log::error!(
+                        "FOK_NOT_FILLED: {} with {} remaining",
+                        order.order_id,
+                        order.remaining_amount
+                    );
+                    (OrderStatus::Failed, OrderEventType::Fail)
                 }
+                _ => (OrderStatus::Cancelled, OrderEventType::Expire),
             };
 
-            match order {
-                Some(order) => {
-                    let (status, amount) = match trade.limit_type {
-                        LimitType::GTC | LimitType::MKT => {
-                            if order.amount > trade.size {
-                                (OrderStatus::PartiallyMatched, Some(order.amount - trade.size))
-                            } else {
-                                (OrderStatus::Matched, None)
-                            }
-                        }
-                        // FOK or IOC as fully matched
-                        _ => (OrderStatus::Matched, None),
-                    };
+            events.push(OrderEvent {
+                order_id: order.order_id.clone(),
+                event_type,
+                tx_id: settlement.tx_id,
+                block_number: settlement.position.block_number as u64,
+                timestamp: settlement.timestamp,
+                amount_before: order.remaining_amount,
+                amount_after: order.remaining_amount,
+                status,
+                market_id: order.market_id.clone(),
+            });
+            settled.push(Order {
+                status,
+                ..order.clone()
+            });
+            undos.push((settlement.position.block_number, order));
+        }
 
-                    if let Err(e) = repo::order::Mutation::update(
-                        &self.db_conn,
-                        UpdateOrder {
-                            order_id: trade.order_id.clone(),
-                            amount,
-                            status,
-                        },
-                    )
-                    .await
-                    {
-                        log::error!("UPDATE_ORDER_ERROR: {}", e);
-                    }
-                }
-                None => {
-                    log::error!("ORDER_NOT_FOUND: {}", trade.order_id);
-                }
-            }
+        repo::order::Mutation::update_fills(db_conn, settled).await?;
+        repo::order_undo::Mutation::insert_many(db_conn, &self.market_id, undos).await?;
+        repo::order_event::Mutation::insert_many(db_conn, events).await?;
+
+        Ok(pending)
+    }
+}
+
+/// Outcome of a batch of trades applied to their orders.
+#[derive(Default)]
+struct Fills {
+    /// Resulting states of the orders which were filled.
+    orders: Vec<Order>,
+    /// Previous states of the orders, one for each fill.
+    undos: Vec<(i64, Order)>,
+    events: Vec<OrderEvent>,
+    settlements: Vec<Settlement>,
+    /// The trades whose order is not found or can't be filled.
+    rejections: Vec<Rejection>,
+    /// The trades which were applied along with their events.
+    trades: Vec<(Arc<PangeaEvent>, Trade)>,
+}
+
+/// Applies a batch of trades to their orders in the order of the trades.
+///
+/// For each trade, it applies the fill to the order's amounts and average fill price, updates its status and
+/// records the previous state of the order along with an event. An order filled several times in the batch
+/// gets each fill applied to the state left by the previous one. A trade whose order is not found is rejected
+/// and skipped, so is a trade whose fill overflows the amounts of its order.
+///
+/// An order which is no longer active or gets more than its remaining amount can't be reconciled with the trade,
+/// it's marked as `Failed`. A partially filled IOC, FOK or MKT order is left to be settled after its transaction.
+///
+/// # Arguments
+///
+/// * `orders` - The orders of the trades by their IDs.
+/// * `trades` - The trades to be applied along with their events.
+///
+fn apply_fills(
+    mut orders: HashMap<String, Order>,
+    trades: Vec<(Arc<PangeaEvent>, Trade)>,
+) -> Fills {
+    let mut fills = Fills::default();
+    let mut filled_ids = Vec::new();
+
+    for (event, trade) in trades {
+        let Some(order) = orders.get(&trade.order_id).cloned() else {
+            log::error!("ORDER_NOT_FOUND: {}", trade.order_id);
+            let rejection = event.reject(format!("ORDER_NOT_FOUND: {}", trade.order_id));
+            fills.rejections.push(rejection);
+            continue;
+        };
+
+        let mut filled = order.clone();
+        if let Err(e) = filled.fill(trade.size, trade.price) {
+            log::error!("FILL_OVERFLOW: {}: {}", trade.order_id, e);
+            let rejection = event.reject(format!("FILL_OVERFLOW: {}: {}", trade.order_id, e));
+            fills.rejections.push(rejection);
+            continue;
+        }
+
+        let status = if !order.is_active() {
+            log::error!(
+                "TRADE_ON_INACTIVE_ORDER: {} is {}",
+                trade.order_id,
+                order.status
+            );
+            OrderStatus::Failed
+        } else if trade.size > order.remaining_amount {
+            log::error!(
+                "ORDER_OVERFILLED: {} by {} with {} remaining",
+                trade.order_id,
+                trade.size,
+                order.remaining_amount
+            );
+            OrderStatus::Failed
+        } else if !filled.remaining_amount.is_zero() {
+            OrderStatus::PartiallyMatched
+        } else {
+            OrderStatus::Matched
+        };
+        filled.status = status;
+
+        let event_type = match status {
+            OrderStatus::PartiallyMatched => OrderEventType::PartialFill,
+            OrderStatus::Matched => OrderEventType::Fill,
+            _ => OrderEventType::Fail,
+        };
+        fills.events.push(OrderEvent {
+            order_id: trade.order_id.clone(),
+            event_type,
+            tx_id: trade.tx_id.clone(),
+            block_number: trade.block_number,
+            timestamp: trade.timestamp,
+            amount_before: order.remaining_amount,
+            amount_after: filled.remaining_amount,
+            status,
+            market_id: order.market_id.clone(),
+        });
+        fills.undos.push((trade.block_number as i64, order));
+
+        if status == OrderStatus::PartiallyMatched && !matches!(trade.limit_type, LimitType::GTC) {
+            fills
+                .settlements
+                .retain(|settlement| settlement.order_id != trade.order_id);
+            fills.settlements.push(Settlement {
+                position: event.position(),
+                order_id: trade.order_id.clone(),
+                limit_type: trade.limit_type,
+                tx_id: trade.tx_id.clone(),
+                timestamp: trade.timestamp,
+            });
         }
 
-        if let Err(e) = repo::trade::Mutation::insert_many(&self.db_conn, trades.clone()).await {
-            log::error!("CREATE_TRADES_ERROR: {}", e);
+        if !filled_ids.contains(&trade.order_id) {
+            filled_ids.push(trade.order_id.clone());
         }
+        orders.insert(trade.order_id.clone(), filled);
+        fills.trades.push((event, trade));
     }
+
+    fills.orders = filled_ids
+        .into_iter()
+        .filter_map(|order_id| orders.remove(&order_id))
+        .collect();
+
+    fills
 }
 
 fn extract_updates<T, F>(updates: &[Update], filter_fn: F) -> Vec<T>
@@ -240,3 +1134,397 @@ where
 {
     updates.iter().filter_map(filter_fn).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use sparker_core::{OrderType, U256};
+
+    fn timestamp() -> NaiveDateTime {
+        DateTime::from_timestamp(0, 0).unwrap().naive_utc()
+    }
+
+    fn amount(value: u128) -> U128 {
+        value.into()
+    }
+
+    fn order(order_id: &str, amount: u128, price: u128) -> Order {
+        Order {
+            tx_id: "0xopen".to_string(),
+            order_id: order_id.to_string(),
+            order_type: OrderType::Buy,
+            user: "0xuser".to_string(),
+            asset: "0xasset".to_string(),
+            initial_amount: amount.into(),
+            filled_amount: U128::ZERO,
+            remaining_amount: amount.into(),
+            price: price.into(),
+            average_fill_price: U128::ZERO,
+            fill_volume: U256::ZERO,
+            status: OrderStatus::New,
+            block_number: 1,
+            timestamp: timestamp(),
+            market_id: "0xmarket".to_string(),
+            decimals: None,
+        }
+    }
+
+    fn orders(orders: Vec<Order>) -> HashMap<String, Order> {
+        orders
+            .into_iter()
+            .map(|order| (order.order_id.clone(), order))
+            .collect()
+    }
+
+    /// A trade of an order in the given transaction of block 10, along with its event.
+    fn trade(
+        order_id: &str,
+        size: u128,
+        price: u128,
+        limit_type: LimitType,
+        (transaction_index, log_index): (u64, u64),
+    ) -> (Arc<PangeaEvent>, Trade) {
+        let tx_id = format!("0xtx{}", transaction_index);
+        let event = PangeaEvent {
+            chain: 0,
+            block_number: 10,
+            block_hash: "0xblock".to_string(),
+            block_timestamp: 0,
+            transaction_hash: tx_id.clone(),
+            transaction_index,
+            log_index,
+            market_id: "0xmarket".to_string(),
+            order_id: order_id.to_string(),
+            event_type: Some("Trade".to_string()),
+            asset: None,
+            amount: Some(size),
+            asset_type: None,
+            order_type: None,
+            price: Some(price),
+            user: None,
+            order_matcher: None,
+            owner: None,
+            limit_type: Some(limit_type.to_string()),
+        };
+        let trade = Trade {
+            tx_id,
+            trade_id: format!("{}-{}-{}", order_id, transaction_index, log_index),
+            order_id: order_id.to_string(),
+            limit_type,
+            user: "0xuser".to_string(),
+            size: size.into(),
+            price: price.into(),
+            block_number: 10,
+            timestamp: timestamp(),
+            market_id: "0xmarket".to_string(),
+            decimals: None,
+        };
+
+        (Arc::new(event), trade)
+    }
+
+    fn settlements(fills: &Fills) -> Vec<(String, Position, String)> {
+        fills
+            .settlements
+            .iter()
+            .map(|settlement| {
+                (
+                    settlement.order_id.clone(),
+                    settlement.position,
+                    settlement.tx_id.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn rejections(fills: &Fills) -> Vec<(Option<i64>, String)> {
+        fills
+            .rejections
+            .iter()
+            .map(|rejection| (rejection.block_number, rejection.reason.clone()))
+            .collect()
+    }
+
+    fn trade_ids(fills: &Fills) -> Vec<String> {
+        fills
+            .trades
+            .iter()
+            .map(|(_, trade)| trade.trade_id.clone())
+            .collect()
+    }
+
+    #[test]
+    fn applies_several_fills_of_an_order_in_order() {
+        let fills = apply_fills(
+            orders(vec![order("0xa", 100, 15)]),
+            vec![
+                trade("0xa", 30, 10, LimitType::GTC, (0, 0)),
+                trade("0xa", 50, 20, LimitType::GTC, (1, 0)),
+            ],
+        );
+
+        assert_eq!(fills.orders.len(), 1);
+        let filled = &fills.orders[0];
+        assert_eq!(filled.filled_amount, amount(80));
+        assert_eq!(filled.remaining_amount, amount(20));
+        assert_eq!(filled.fill_volume, U256::from(amount(1300)));
+        assert_eq!(filled.average_fill_price, amount(16));
+        assert_eq!(filled.status, OrderStatus::PartiallyMatched);
+
+        // Each fill is applied to the state left by the previous one
+        let undone_amounts = fills
+            .undos
+            .iter()
+            .map(|(_, order)| order.filled_amount)
+            .collect::<Vec<_>>();
+        assert_eq!(undone_amounts, vec![U128::ZERO, amount(30)]);
+        let amounts = fills
+            .events
+            .iter()
+            .map(|event| (event.amount_before, event.amount_after))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            amounts,
+            vec![(amount(100), amount(70)), (amount(70), amount(20)),]
+        );
+        assert_eq!(trade_ids(&fills), vec!["0xa-0-0", "0xa-1-0"]);
+        assert!(fills.rejections.is_empty());
+    }
+
+    #[test]
+    fn rejects_a_trade_whose_order_is_not_found() {
+        let fills = apply_fills(
+            orders(vec![order("0xa", 100, 10)]),
+            vec![
+                trade("0xb", 10, 10, LimitType::GTC, (0, 0)),
+                trade("0xa", 100, 10, LimitType::GTC, (0, 1)),
+            ],
+        );
+
+        assert_eq!(
+            rejections(&fills),
+            vec![(Some(10), "ORDER_NOT_FOUND: 0xb".to_string())]
+        );
+        assert_eq!(trade_ids(&fills), vec!["0xa-0-1"]);
+        assert_eq!(fills.orders.len(), 1);
+        assert_eq!(fills.orders[0].status, OrderStatus::Matched);
+        assert_eq!(fills.events.len(), 1);
+    }
+
+    #[test]
+    fn fails_an_overfilled_order() {
+        let fills = apply_fills(
+            orders(vec![order("0xa", 100, 10)]),
+            vec![
+                trade("0xa", 60, 10, LimitType::GTC, (0, 0)),
+                trade("0xa", 60, 10, LimitType::GTC, (1, 0)),
+                trade("0xa", 10, 10, LimitType::GTC, (2, 0)),
+            ],
+        );
+
+        let statuses = fills
+            .events
+            .iter()
+            .map(|event| (event.event_type, event.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                (OrderEventType::PartialFill, OrderStatus::PartiallyMatched),
+                (OrderEventType::Fail, OrderStatus::Failed),
+                // The order is no longer active
+                (OrderEventType::Fail, OrderStatus::Failed),
+            ]
+        );
+        let filled = &fills.orders[0];
+        assert_eq!(filled.status, OrderStatus::Failed);
+        assert_eq!(filled.filled_amount, amount(130));
+        assert_eq!(filled.remaining_amount, U128::ZERO);
+        assert!(fills.settlements.is_empty());
+    }
+
+    #[test]
+    fn settles_the_partially_filled_ioc_and_fok_orders() {
+        let fills = apply_fills(
+            orders(vec![
+                order("0xioc", 100, 10),
+                order("0xfok", 100, 10),
+                order("0xgtc", 100, 10),
+                order("0xfilled", 100, 10),
+            ]),
+            vec![
+                trade("0xioc", 20, 10, LimitType::IOC, (0, 0)),
+                trade("0xfok", 20, 10, LimitType::FOK, (0, 1)),
+                trade("0xgtc", 20, 10, LimitType::GTC, (0, 2)),
+                trade("0xfilled", 100, 10, LimitType::IOC, (0, 3)),
+                trade("0xioc", 30, 10, LimitType::IOC, (1, 0)),
+            ],
+        );
+
+        // An order filled again is settled after its last fill only
+        assert_eq!(
+            settlements(&fills),
+            vec![
+                (
+                    "0xfok".to_string(),
+                    Position {
+                        block_number: 10,
+                        transaction_index: 0,
+                        log_index: 1,
+                    },
+                    "0xtx0".to_string()
+                ),
+                (
+                    "0xioc".to_string(),
+                    Position {
+                        block_number: 10,
+                        transaction_index: 1,
+                        log_index: 0,
+                    },
+                    "0xtx1".to_string()
+                ),
+            ]
+        );
+        assert!(matches!(fills.settlements[0].limit_type, LimitType::FOK));
+        assert!(matches!(fills.settlements[1].limit_type, LimitType::IOC));
+    }
+
+    #[test]
+    fn applies_a_batch_of_fills_of_several_orders() {
+        let mut cancelled = order("0xcancelled", 100, 10);
+        cancelled.status = OrderStatus::Cancelled;
+
+        let fills = apply_fills(
+            orders(vec![order("0xa", 100, 10), order("0xb", 50, 20), cancelled]),
+            vec![
+                trade("0xa", 40, 10, LimitType::GTC, (0, 0)),
+                trade("0xmissing", 10, 10, LimitType::GTC, (0, 1)),
+                trade("0xb", 80, 20, LimitType::GTC, (1, 0)),
+                trade("0xcancelled", 10, 10, LimitType::GTC, (1, 1)),
+                trade("0xa", 60, 15, LimitType::GTC, (2, 0)),
+            ],
+        );
+
+        let events = fills
+            .events
+            .iter()
+            .map(|event| {
+                (
+                    event.order_id.as_str(),
+                    event.event_type,
+                    event.status,
+                    event.amount_before,
+                    event.amount_after,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    "0xa",
+                    OrderEventType::PartialFill,
+                    OrderStatus::PartiallyMatched,
+                    amount(100),
+                    amount(60),
+                ),
+                // Overfilled
+                (
+                    "0xb",
+                    OrderEventType::Fail,
+                    OrderStatus::Failed,
+                    amount(50),
+                    U128::ZERO,
+                ),
+                // Filled while inactive
+                (
+                    "0xcancelled",
+                    OrderEventType::Fail,
+                    OrderStatus::Failed,
+                    amount(100),
+                    amount(90),
+                ),
+                (
+                    "0xa",
+                    OrderEventType::Fill,
+                    OrderStatus::Matched,
+                    amount(60),
+                    U128::ZERO,
+                ),
+            ]
+        );
+
+        let orders = fills
+            .orders
+            .iter()
+            .map(|order| {
+                (
+                    order.order_id.as_str(),
+                    order.filled_amount,
+                    order.remaining_amount,
+                    order.fill_volume,
+                    order.average_fill_price,
+                    order.status,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            orders,
+            vec![
+                // 40 at 10 then 60 at 15
+                (
+                    "0xa",
+                    amount(100),
+                    U128::ZERO,
+                    U256::from(amount(1300)),
+                    amount(13),
+                    OrderStatus::Matched,
+                ),
+                (
+                    "0xb",
+                    amount(80),
+                    U128::ZERO,
+                    U256::from(amount(1600)),
+                    amount(20),
+                    OrderStatus::Failed,
+                ),
+                (
+                    "0xcancelled",
+                    amount(10),
+                    amount(90),
+                    U256::from(amount(100)),
+                    amount(10),
+                    OrderStatus::Failed,
+                ),
+            ]
+        );
+
+        let undos = fills
+            .undos
+            .iter()
+            .map(|(block_number, order)| {
+                (*block_number, order.order_id.as_str(), order.filled_amount)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            undos,
+            vec![
+                (10, "0xa", U128::ZERO),
+                (10, "0xb", U128::ZERO),
+                (10, "0xcancelled", U128::ZERO),
+                (10, "0xa", amount(40)),
+            ]
+        );
+
+        assert_eq!(
+            rejections(&fills),
+            vec![(Some(10), "ORDER_NOT_FOUND: 0xmissing".to_string())]
+        );
+        assert_eq!(
+            trade_ids(&fills),
+            vec!["0xa-0-0", "0xb-1-0", "0xcancelled-1-1", "0xa-2-0"]
+        );
+        assert!(fills.settlements.is_empty());
+    }
+}
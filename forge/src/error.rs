@@ -15,13 +15,38 @@ pub enum Error {
     Database(#[from] sea_orm::DbErr),
 
     #[error("Pangea: {0}")]
-    PangeaClient(#[from] pangea_client::Error),
+    PangeaClient(Box<pangea_client::Error>),
 
     #[error("Fuel: {0}")]
     Fuel(#[from] fuels::types::errors::Error),
 
-    #[error("Invalid fuel chain id")]
-    InvalidChainId,
+    #[error("Market contract: {0}")]
+    Contract(String),
+
+    #[error("Task: {0}")]
+    Task(#[from] tokio::task::JoinError),
+
+    #[error("Event stream closed unexpectedly")]
+    StreamClosed,
+
+    #[error("Dispatcher of market {0} stopped")]
+    DispatcherStopped(String),
+
+    #[error("Config: {0}")]
+    Config(String),
+
+    #[error("Market {0} is indexed by another instance")]
+    MarketLocked(String),
+
+    #[error("Lease of market {0} was lost")]
+    LeaseLost(String),
+}
+
+// The Pangea errors are boxed, they would make every result of forge several times larger
+impl From<pangea_client::Error> for Error {
+    fn from(err: pangea_client::Error) -> Self {
+        Error::PangeaClient(Box::new(err))
+    }
 }
 
 #[derive(Error, Debug)]
@@ -0,0 +1,309 @@
+use fuels::types::{AssetId, Bits256, Identity};
+use rustc_hex::ToHex;
+use spark_market_sdk::{CancelOrderEvent, LimitType, OpenOrderEvent, OrderType, TradeOrderEvent};
+use std::fmt::Display;
+
+use crate::{
+    pangea::event::{PangeaEvent, INVALID_EVENT},
+    types::Rejection,
+};
+
+/// Block and transaction of a log emitted by the market contract.
+#[derive(Debug, Clone)]
+pub struct LogContext {
+    pub chain: u64,
+    pub block_number: i64,
+    pub block_hash: String,
+    pub block_timestamp: i64,
+    pub transaction_hash: String,
+    pub transaction_index: u64,
+    /// Index of the log receipt within the transaction, the log index of its events is derived
+    /// from it since a trade log makes two events.
+    pub receipt_index: u64,
+    pub market_id: String,
+}
+
+impl LogContext {
+    /// Builds an event of the log without any of the order fields.
+    fn event(&self, order_id: &Bits256, log_index: u64) -> PangeaEvent {
+        PangeaEvent {
+            chain: self.chain,
+            block_number: self.block_number,
+            block_hash: self.block_hash.clone(),
+            block_timestamp: self.block_timestamp,
+            transaction_hash: self.transaction_hash.clone(),
+            transaction_index: self.transaction_index,
+            log_index,
+            market_id: self.market_id.clone(),
+            order_id: bits_to_hex(order_id),
+            event_type: None,
+            asset: None,
+            amount: None,
+            asset_type: None,
+            order_type: None,
+            price: None,
+            user: None,
+            order_matcher: None,
+            owner: None,
+            limit_type: None,
+        }
+    }
+
+    /// Decodes the opening of an order.
+    pub fn open_order(&self, log: OpenOrderEvent) -> PangeaEvent {
+        PangeaEvent {
+            event_type: Some("Open".to_string()),
+            asset: Some(asset_to_hex(&log.asset)),
+            amount: Some(log.amount as u128),
+            order_type: Some(order_type_name(log.order_type).to_string()),
+            price: Some(log.price as u128),
+            user: Some(identity_to_hex(&log.user)),
+            ..self.event(&log.order_id, self.receipt_index * 2)
+        }
+    }
+
+    /// Decodes the cancellation of an order.
+    pub fn cancel_order(&self, log: CancelOrderEvent) -> PangeaEvent {
+        PangeaEvent {
+            event_type: Some("Cancel".to_string()),
+            user: Some(identity_to_hex(&log.user)),
+            ..self.event(&log.order_id, self.receipt_index * 2)
+        }
+    }
+
+    /// Rejects a log which can't be decoded, its raw data is kept as the payload.
+    pub fn reject(&self, data: &[u8], error: impl Display) -> Rejection {
+        Rejection {
+            block_number: Some(self.block_number),
+            payload: format!("0x{}", data.to_hex::<String>()),
+            reason: format!(
+                "{}: log {} of transaction {}: {}",
+                INVALID_EVENT, self.receipt_index, self.transaction_hash, error
+            ),
+        }
+    }
+
+    /// Decodes a trade, which fills both the sell and the buy order.
+    ///
+    /// Every side gets an event of its own, like in Pangea, the sell side first.
+    pub fn trade_order(&self, log: TradeOrderEvent) -> [PangeaEvent; 2] {
+        let trade = |order_id: &Bits256, limit_type, user: &Identity, log_index| PangeaEvent {
+            event_type: Some("Trade".to_string()),
+            amount: Some(log.trade_size as u128),
+            price: Some(log.trade_price as u128),
+            user: Some(identity_to_hex(user)),
+            order_matcher: Some(identity_to_hex(&log.order_matcher)),
+            limit_type: Some(limit_type_name(limit_type).to_string()),
+            ..self.event(order_id, log_index)
+        };
+
+        [
+            trade(
+                &log.base_sell_order_id,
+                log.base_sell_order_limit,
+                &log.order_seller,
+                self.receipt_index * 2,
+            ),
+            trade(
+                &log.base_buy_order_id,
+                log.base_buy_order_limit,
+                &log.order_buyer,
+                self.receipt_index * 2 + 1,
+            ),
+        ]
+    }
+}
+
+fn bits_to_hex(bits: &Bits256) -> String {
+    format!("0x{}", bits.0.to_hex::<String>())
+}
+
+fn asset_to_hex(asset: &AssetId) -> String {
+    format!("{:#x}", asset)
+}
+
+fn identity_to_hex(identity: &Identity) -> String {
+    match identity {
+        Identity::Address(address) => format!("{:#x}", address),
+        Identity::ContractId(contract_id) => format!("{:#x}", contract_id),
+    }
+}
+
+fn order_type_name(order_type: OrderType) -> &'static str {
+    match order_type {
+        OrderType::Buy => "Buy",
+        OrderType::Sell => "Sell",
+    }
+}
+
+fn limit_type_name(limit_type: LimitType) -> &'static str {
+    match limit_type {
+        LimitType::GTC => "GTC",
+        LimitType::IOC => "IOC",
+        LimitType::FOK => "FOK",
+        LimitType::MKT => "MKT",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fuels::types::{Address, ContractId};
+    use spark_market_sdk::{Account, Balance};
+    use std::slice;
+
+    fn context(receipt_index: u64) -> LogContext {
+        LogContext {
+            chain: 9889,
+            block_number: 10,
+            block_hash: "0xblock".to_string(),
+            block_timestamp: 1_700_000_000,
+            transaction_hash: "0xtx".to_string(),
+            transaction_index: 3,
+            receipt_index,
+            market_id: "0xmarket".to_string(),
+        }
+    }
+
+    fn account() -> Account {
+        let balance = Balance { base: 0, quote: 0 };
+        Account {
+            liquid: balance.clone(),
+            locked: balance,
+        }
+    }
+
+    fn hex(byte: u8) -> String {
+        format!("0x{}", format!("{:02x}", byte).repeat(32))
+    }
+
+    fn address(byte: u8) -> Identity {
+        Identity::Address(Address::new([byte; 32]))
+    }
+
+    fn positions(events: &[PangeaEvent]) -> Vec<(i64, u64, u64)> {
+        events
+            .iter()
+            .map(|event| (event.block_number, event.transaction_index, event.log_index))
+            .collect()
+    }
+
+    #[test]
+    fn maps_an_open_order_log() {
+        let event = context(2).open_order(OpenOrderEvent {
+            amount: 500,
+            asset: AssetId::new([0xaa; 32]),
+            order_type: OrderType::Sell,
+            order_id: Bits256([0x01; 32]),
+            price: 70_000,
+            user: address(0xbb),
+            balance: account(),
+            limit_type: LimitType::GTC,
+        });
+
+        assert_eq!(positions(slice::from_ref(&event)), [(10, 3, 4)]);
+        assert_eq!(event.chain, 9889);
+        assert_eq!(event.block_hash, "0xblock");
+        assert_eq!(event.block_timestamp, 1_700_000_000);
+        assert_eq!(event.transaction_hash, "0xtx");
+        assert_eq!(event.market_id, "0xmarket");
+        assert_eq!(event.order_id, hex(0x01));
+        assert_eq!(event.event_type.as_deref(), Some("Open"));
+        assert_eq!(event.asset, Some(hex(0xaa)));
+        assert_eq!(event.amount, Some(500));
+        assert_eq!(event.order_type.as_deref(), Some("Sell"));
+        assert_eq!(event.price, Some(70_000));
+        assert_eq!(event.user, Some(hex(0xbb)));
+        assert_eq!(event.limit_type, None);
+    }
+
+    #[test]
+    fn maps_a_cancel_order_log() {
+        let event = context(0).cancel_order(CancelOrderEvent {
+            order_id: Bits256([0x02; 32]),
+            user: Identity::ContractId(ContractId::new([0xcc; 32])),
+            balance: account(),
+        });
+
+        assert_eq!(positions(slice::from_ref(&event)), [(10, 3, 0)]);
+        assert_eq!(event.order_id, hex(0x02));
+        assert_eq!(event.event_type.as_deref(), Some("Cancel"));
+        assert_eq!(event.user, Some(hex(0xcc)));
+        assert_eq!(event.amount, None);
+        assert_eq!(event.price, None);
+    }
+
+    #[test]
+    fn maps_a_trade_log_to_an_event_of_each_side() {
+        let events = context(5).trade_order(TradeOrderEvent {
+            base_sell_order_id: Bits256([0x03; 32]),
+            base_buy_order_id: Bits256([0x04; 32]),
+            base_sell_order_limit: LimitType::GTC,
+            base_buy_order_limit: LimitType::IOC,
+            order_matcher: address(0xdd),
+            trade_size: 20,
+            trade_price: 69_000,
+            block_height: 10,
+            tx_id: Bits256([0x05; 32]),
+            order_seller: address(0xee),
+            order_buyer: address(0xff),
+            s_balance: account(),
+            b_balance: account(),
+            seller_is_maker: true,
+        });
+
+        // The sell side first, the log index of both sides derived from the receipt index
+        assert_eq!(positions(&events), [(10, 3, 10), (10, 3, 11)]);
+
+        let [sell, buy] = events;
+        let sides = [(sell, 0x03, "GTC", 0xee), (buy, 0x04, "IOC", 0xff)];
+        for (event, order_id, limit_type, user) in sides {
+            assert_eq!(event.order_id, hex(order_id));
+            assert_eq!(event.event_type.as_deref(), Some("Trade"));
+            assert_eq!(event.amount, Some(20));
+            assert_eq!(event.price, Some(69_000));
+            assert_eq!(event.limit_type.as_deref(), Some(limit_type));
+            assert_eq!(event.user, Some(hex(user)));
+            assert_eq!(event.order_matcher, Some(hex(0xdd)));
+        }
+    }
+
+    #[test]
+    fn numbers_the_logs_of_a_transaction_apart() {
+        let trade_log_indexes = (0..3)
+            .flat_map(|receipt_index| {
+                let events = context(receipt_index).trade_order(TradeOrderEvent {
+                    base_sell_order_id: Bits256([0x03; 32]),
+                    base_buy_order_id: Bits256([0x04; 32]),
+                    base_sell_order_limit: LimitType::GTC,
+                    base_buy_order_limit: LimitType::GTC,
+                    order_matcher: address(0xdd),
+                    trade_size: 1,
+                    trade_price: 1,
+                    block_height: 10,
+                    tx_id: Bits256([0x05; 32]),
+                    order_seller: address(0xee),
+                    order_buyer: address(0xff),
+                    s_balance: account(),
+                    b_balance: account(),
+                    seller_is_maker: false,
+                });
+                events.map(|event| event.log_index)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(trade_log_indexes, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn rejects_an_undecodable_log_with_its_data() {
+        let rejection = context(7).reject(&[0xde, 0xad], "unknown log");
+
+        assert_eq!(rejection.block_number, Some(10));
+        assert_eq!(rejection.payload, "0xdead");
+        assert_eq!(
+            rejection.reason,
+            "INVALID_EVENT: log 7 of transaction 0xtx: unknown log"
+        );
+    }
+}
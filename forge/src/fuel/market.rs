@@ -0,0 +1,123 @@
+use fuels::{
+    accounts::{provider::Provider, wallet::WalletUnlocked},
+    programs::calls::Execution,
+    types::{AssetId, ContractId, Identity},
+};
+use spark_market_sdk::{SparkMarket, SparkProxy};
+use std::str::FromStr;
+
+use crate::{config::Config, error::Error};
+
+/// Value of the `config` method of a market contract: the base asset and its decimals, the
+/// quote asset and its decimals, the owner, the decimals of the prices and the version.
+type MarketConfig = (AssetId, u32, AssetId, u32, Option<Identity>, u32, u32);
+
+/// Assets and decimals of a market as its contract is configured with.
+#[derive(Debug, Clone)]
+pub struct ContractConfig {
+    pub base_asset: String,
+    pub base_decimals: u32,
+    pub quote_asset: String,
+    pub quote_decimals: u32,
+    pub price_decimals: u32,
+}
+
+impl From<MarketConfig> for ContractConfig {
+    fn from(config: MarketConfig) -> Self {
+        let (base_asset, base_decimals, quote_asset, quote_decimals, _owner, price_decimals, _) =
+            config;
+
+        Self {
+            base_asset: format!("{:#x}", base_asset),
+            base_decimals,
+            quote_asset: format!("{:#x}", quote_asset),
+            quote_decimals,
+            price_decimals,
+        }
+    }
+}
+
+/// Reads the config of a market contract from a Fuel node.
+///
+/// The market is called through its proxy, with the implementation the proxy points to, as
+/// `SparkMarketContract` does. The SDK contract isn't used since it panics on a failed read.
+pub async fn read_config(config: &Config, market_id: &str) -> Result<ContractConfig, Error> {
+    let provider = Provider::connect(config.fuel_url()).await?;
+    let contract_id = ContractId::from_str(market_id)
+        .map_err(|e| Error::Config(format!("Invalid market id {}: {}", market_id, e)))?;
+
+    // The wallet is never used to sign, the config is read without a transaction
+    let wallet = WalletUnlocked::new_random(Some(provider));
+
+    // A market deployed without a proxy is its own implementation
+    let implementation = SparkProxy::new(contract_id, wallet.clone())
+        .methods()
+        .proxy_target()
+        .simulate(Execution::StateReadOnly)
+        .await
+        .ok()
+        .and_then(|response| response.value)
+        .unwrap_or(contract_id);
+
+    let market_config = SparkMarket::new(contract_id, wallet)
+        .methods()
+        .config()
+        .with_contract_ids(&[implementation.into()])
+        .simulate(Execution::StateReadOnly)
+        .await
+        .map_err(|e| Error::Contract(e.to_string()))?
+        .value;
+
+    Ok(market_config.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fuels::{
+        core::{
+            codec::ABIDecoder,
+            traits::{Parameterize, Tokenizable},
+        },
+        types::Address,
+    };
+
+    /// Encoded `config` of a market of 9 decimals base asset `0x11..11`, 6 decimals quote asset
+    /// `0x22..22`, owned by address `0x33..33`, with 9 decimals prices and version 0x000701.
+    fn encoded_config() -> Vec<u8> {
+        [
+            [0x11; 32].as_slice(),
+            &9u32.to_be_bytes(),
+            &[0x22; 32],
+            &6u32.to_be_bytes(),
+            // `Some` of an `Address` identity
+            &1u64.to_be_bytes(),
+            &0u64.to_be_bytes(),
+            &[0x33; 32],
+            &9u32.to_be_bytes(),
+            &0x000701u32.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decodes_the_config_of_a_market() {
+        let token = ABIDecoder::default()
+            .decode(&MarketConfig::param_type(), &encoded_config())
+            .unwrap();
+        let market_config = MarketConfig::from_token(token).unwrap();
+
+        assert_eq!(
+            market_config.4,
+            Some(Identity::Address(Address::new([0x33; 32])))
+        );
+        assert_eq!(market_config.6, 0x000701);
+
+        let config = ContractConfig::from(market_config);
+        assert_eq!(config.base_asset, format!("0x{}", "11".repeat(32)));
+        assert_eq!(config.base_decimals, 9);
+        assert_eq!(config.quote_asset, format!("0x{}", "22".repeat(32)));
+        assert_eq!(config.quote_decimals, 6);
+        assert_eq!(config.price_decimals, 9);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod event;
+pub mod market;
+pub mod source;
+
+pub use source::*;
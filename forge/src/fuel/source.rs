@@ -0,0 +1,236 @@
+use fuels::{
+    accounts::{provider::Provider, wallet::WalletUnlocked},
+    core::codec::LogDecoder,
+    programs::calls::ContractDependency,
+    tx::Receipt,
+    types::{tx_status::TxStatus, ContractId},
+};
+use futures::{future::BoxFuture, stream, FutureExt, StreamExt, TryStreamExt};
+use spark_market_sdk::{CancelOrderEvent, OpenOrderEvent, SparkMarket, TradeOrderEvent};
+use std::{slice, str::FromStr, sync::Arc};
+use tokio::time::{sleep, Duration};
+
+use crate::{
+    config::Config,
+    error::Error,
+    fuel::event::LogContext,
+    pangea::event::PangeaEvent,
+    source::{EventSource, EventStream, SourceEvent},
+};
+
+/// Number of blocks requested from the node at once.
+const BLOCK_CONCURRENCY: usize = 16;
+/// Interval between the checks for new blocks while following the chain head.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Event source which reads the logs of the market contract straight from a Fuel node.
+///
+/// The receipts of every transaction are fetched through the GraphQL API of the node and the
+/// logs of the market are decoded with the ABI of the contract, so the events are the same as
+/// the Pangea ones. It doesn't depend on anything but the node, e.g. a local `fuel-core`.
+pub struct FuelSource {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    provider: Provider,
+    decoder: LogDecoder,
+    contract_id: ContractId,
+    market_id: String,
+}
+
+impl FuelSource {
+    pub async fn create(config: &Config, market_id: &str) -> Result<Self, Error> {
+        let provider = Provider::connect(config.fuel_url()).await?;
+        let contract_id = ContractId::from_str(market_id)
+            .map_err(|e| Error::Config(format!("Invalid market id {}: {}", market_id, e)))?;
+
+        // The wallet is never used, the bindings of the contract are only needed for their log
+        // decoder. They are built without reading the contract, unlike `SparkMarketContract`
+        let wallet = WalletUnlocked::new_random(Some(provider.clone()));
+        let decoder = SparkMarket::new(contract_id, wallet).log_decoder();
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                provider,
+                decoder,
+                contract_id,
+                market_id: market_id.to_string(),
+            }),
+        })
+    }
+}
+
+impl Inner {
+    /// Reads the market events of a block, in the order they were emitted.
+    ///
+    /// A log which can't be decoded is rejected on its own, the rest of the block is read.
+    ///
+    /// # Returns
+    ///
+    /// Returns no events if the block doesn't exist yet.
+    async fn block_events(&self, block_number: i64) -> Result<Vec<SourceEvent>, Error> {
+        let Some(block) = self
+            .provider
+            .block_by_height((block_number as u32).into())
+            .await?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let mut events = Vec::new();
+        for (transaction_index, tx_id) in block.transactions.iter().enumerate() {
+            let Some(tx) = self.provider.get_transaction_by_id(tx_id).await? else {
+                continue;
+            };
+            // The logs of a reverted transaction never took effect
+            let TxStatus::Success { receipts } = tx.status else {
+                continue;
+            };
+
+            for (receipt_index, receipt) in receipts.iter().enumerate() {
+                if !matches!(receipt, Receipt::LogData { id, .. } if *id == self.contract_id) {
+                    continue;
+                }
+
+                let context = LogContext {
+                    chain: u64::from(self.provider.chain_id()),
+                    block_number,
+                    block_hash: format!("{:#x}", block.id),
+                    block_timestamp: block.header.time.map_or(0, |time| time.timestamp()),
+                    transaction_hash: format!("{:#x}", tx_id),
+                    transaction_index: transaction_index as u64,
+                    receipt_index: receipt_index as u64,
+                    market_id: self.market_id.clone(),
+                };
+                match self.decode_log(&context, receipt) {
+                    Ok(decoded) => events.extend(decoded.into_iter().map(Ok)),
+                    Err(e) => {
+                        log::error!("[{}] UNDECODABLE_LOG: {}", self.market_id, e);
+                        events.push(Err(context.reject(receipt.data().unwrap_or_default(), e)));
+                    }
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Decodes a log of the market contract, the logs which aren't market events are skipped.
+    fn decode_log(
+        &self,
+        context: &LogContext,
+        receipt: &Receipt,
+    ) -> Result<Vec<PangeaEvent>, Error> {
+        let receipts = slice::from_ref(receipt);
+
+        if let Some(log) = self
+            .decoder
+            .decode_logs_with_type::<OpenOrderEvent>(receipts)?
+            .pop()
+        {
+            return Ok(vec![context.open_order(log)]);
+        }
+        if let Some(log) = self
+            .decoder
+            .decode_logs_with_type::<CancelOrderEvent>(receipts)?
+            .pop()
+        {
+            return Ok(vec![context.cancel_order(log)]);
+        }
+        if let Some(log) = self
+            .decoder
+            .decode_logs_with_type::<TradeOrderEvent>(receipts)?
+            .pop()
+        {
+            return Ok(Vec::from(context.trade_order(log)));
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Reads the market events of the `[from_block, to_block]` range.
+    ///
+    /// Blocks are requested concurrently but yielded in order. A node error is yielded and
+    /// ends the stream.
+    fn read_events(self: Arc<Self>, from_block: i64, to_block: i64) -> EventStream {
+        stream::iter(from_block..=to_block)
+            .map(move |block_number| {
+                let inner = Arc::clone(&self);
+                async move { inner.block_events(block_number).await }
+            })
+            .buffered(BLOCK_CONCURRENCY)
+            .inspect_err(|e| log::error!("Error while reading the Fuel node: {e}"))
+            .scan(false, |failed, events| {
+                // Nothing is yielded past the first error
+                let item = (!*failed).then_some(events);
+                *failed = item.as_ref().is_some_and(Result::is_err);
+                futures::future::ready(item)
+            })
+            .map_ok(|events| stream::iter(events.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Reads the market events starting from a block and following the chain head.
+    ///
+    /// A node error is yielded and ends the stream.
+    fn follow_events(self: Arc<Self>, from_block: i64) -> EventStream {
+        stream::unfold(Some(from_block), move |block_number| {
+            let inner = Arc::clone(&self);
+
+            async move {
+                let block_number = block_number?;
+                let events = inner.follow_block(block_number).await;
+                let next = events.is_ok().then_some(block_number + 1);
+                let items = match events {
+                    Ok(events) => events.into_iter().map(Ok).collect(),
+                    Err(e) => {
+                        log::error!("Error while reading the Fuel node: {e}");
+                        vec![Err(e)]
+                    }
+                };
+
+                Some((stream::iter(items), next))
+            }
+        })
+        .flatten()
+        .boxed()
+    }
+
+    /// Waits for a block to be produced and reads its market events.
+    async fn follow_block(&self, block_number: i64) -> Result<Vec<SourceEvent>, Error> {
+        while block_number > self.provider.latest_block_height().await? as i64 {
+            sleep(POLL_INTERVAL).await;
+        }
+
+        self.block_events(block_number).await
+    }
+}
+
+impl EventSource for FuelSource {
+    fn latest_block(&self) -> BoxFuture<'_, Result<i64, Error>> {
+        async move { Ok(self.inner.provider.latest_block_height().await? as i64) }.boxed()
+    }
+
+    fn block_hash(&self, block_number: i64) -> BoxFuture<'_, Result<Option<String>, Error>> {
+        async move {
+            let block = self
+                .inner
+                .provider
+                .block_by_height((block_number as u32).into())
+                .await?;
+
+            Ok(block.map(|block| format!("{:#x}", block.id)))
+        }
+        .boxed()
+    }
+
+    fn fetch(&self, from_block: i64, to_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move { Ok(Arc::clone(&self.inner).read_events(from_block, to_block)) }.boxed()
+    }
+
+    fn subscribe(&self, from_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move { Ok(Arc::clone(&self.inner).follow_events(from_block)) }.boxed()
+    }
+}
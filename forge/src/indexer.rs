@@ -0,0 +1,578 @@
+use chrono::Utc;
+use futures::{stream, StreamExt};
+use sparker_core::IndexerMode;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{
+    sync::mpsc::error::TrySendError,
+    time::{sleep, Duration, Instant},
+};
+
+use crate::{
+    config::ReconnectPolicy,
+    dispatcher::{Operation, Update, UpdateKind},
+    error::Error,
+    metrics::METRICS,
+    pangea::event::PangeaEvent,
+    source::EventSource,
+    status::StatusReporter,
+    types::{stopped, Rejection, Sender, StopSignal},
+};
+
+/// Interval between the polls of the chain head while listening for new events.
+const CHAIN_HEAD_POLL_INTERVAL: Duration = Duration::from_secs(15);
+/// Number of the latest blocks with events whose hashes are kept to find the fork point of a
+/// reorganization.
+const RECENT_BLOCKS: usize = 64;
+
+/// Drives an [`EventSource`] for a single market and turns its events into dispatcher
+/// operations.
+pub struct Indexer {
+    source: Box<dyn EventSource>,
+    operation_tx: Sender<Operation>,
+    market_name: String,
+    /// Number of blocks fetched at once while catching up.
+    batch_size: u64,
+    /// Number of windows of blocks fetched ahead while catching up.
+    concurrency: usize,
+    reconnect: ReconnectPolicy,
+    /// Reporter of the status of the market, if it's tracked.
+    status: Option<Arc<StatusReporter>>,
+    /// Numbers and hashes of the latest blocks handed to the dispatcher.
+    recent_blocks: Mutex<VecDeque<(i64, String)>>,
+    /// Block number of the latest event received while listening.
+    last_event_block: AtomicI64,
+    /// Number of the open subscription to new events, `0` while there's none.
+    subscription: AtomicU64,
+}
+
+impl Indexer {
+    pub fn new(
+        source: Box<dyn EventSource>,
+        market_name: &str,
+        operation_tx: Sender<Operation>,
+        batch_size: u64,
+        concurrency: usize,
+        reconnect: ReconnectPolicy,
+        status: Option<Arc<StatusReporter>>,
+    ) -> Self {
+        Self {
+            source,
+            operation_tx,
+            market_name: market_name.to_string(),
+            batch_size,
+            concurrency,
+            reconnect,
+            status,
+            recent_blocks: Mutex::new(VecDeque::new()),
+            last_event_block: AtomicI64::new(0),
+            subscription: AtomicU64::new(0),
+        }
+    }
+
+    /// Indexes the market from the latest processed block until it's stopped.
+    ///
+    /// Stopping drops the streams of events along with the operations of an incomplete block,
+    /// the blocks which were dispatched are still committed by the dispatcher. The indexer is
+    /// consumed so the dispatcher stops once it's done.
+    ///
+    /// # Arguments
+    ///
+    /// * `latest_processed_block` - The block number of the latest processed block.
+    /// * `stop` - The signal to stop indexing.
+    ///
+    pub async fn start(
+        self,
+        latest_processed_block: i64,
+        mut stop: StopSignal,
+    ) -> Result<(), Error> {
+        tokio::select! {
+            result = self.run(latest_processed_block) => result,
+            _ = stopped(&mut stop) => {
+                log::info!("[{}] INDEXER_STOPPED", self.market_name);
+                Ok(())
+            }
+        }
+    }
+
+    async fn run(&self, latest_processed_block: i64) -> Result<(), Error> {
+        // Get latest block number from the source
+        let latest_block = self.source.latest_block().await?;
+        self.report_chain_head(latest_block);
+        self.report(|status| status.set_mode(IndexerMode::CatchingUp));
+
+        self.prune(latest_processed_block).await?;
+        let latest_processed_block = self.catch_up(latest_processed_block, latest_block).await?;
+
+        log::info!(
+            "[{}] LISTEN EVENTS FROM BLOCK: {}",
+            self.market_name,
+            latest_processed_block
+        );
+        tokio::select! {
+            result = self.listen_events(latest_processed_block) => result?,
+            result = self.poll_chain_head() => result?,
+        }
+
+        Ok(())
+    }
+
+    /// Polls the latest block of the source to keep track of the lag of the market. The latest
+    /// error is cleared once the chain head is known again while the subscription is open.
+    ///
+    /// When the subscription stays open without new events for a whole interval, the market
+    /// caught up with the chain head of the previous poll. Once that chain head is past the
+    /// block of the latest event, the block is complete. It's dispatched so the orders filled
+    /// by its last transaction are settled without waiting for the next event of the market.
+    async fn poll_chain_head(&self) -> Result<(), Error> {
+        let mut previous_poll = None;
+        let mut completed_block = None;
+
+        loop {
+            sleep(CHAIN_HEAD_POLL_INTERVAL).await;
+
+            match self.source.latest_block().await {
+                Ok(latest_block) => {
+                    let polled_at = Utc::now().timestamp();
+                    self.report_chain_head(latest_block);
+
+                    let activity = (
+                        self.last_event_block.load(Ordering::Relaxed),
+                        self.subscription.load(Ordering::Relaxed),
+                    );
+                    let (last_event_block, subscription) = activity;
+                    if subscription != 0 {
+                        self.report(|status| status.clear_error());
+                    }
+                    if let Some((_, previous_head, previous_polled_at)) =
+                        previous_poll.filter(|(previous_activity, _, _)| {
+                            subscription != 0 && *previous_activity == activity
+                        })
+                    {
+                        METRICS.set_idle_block(
+                            &self.market_name,
+                            previous_head,
+                            previous_polled_at,
+                        );
+                        if previous_head > last_event_block
+                            && completed_block != Some(last_event_block)
+                        {
+                            self.send(Operation::Dispatch(last_event_block + 1)).await?;
+                            completed_block = Some(last_event_block);
+                        }
+                    }
+                    previous_poll = Some((activity, latest_block, polled_at));
+                }
+                Err(e) => {
+                    log::warn!("[{}] CHAIN_HEAD_UNKNOWN: {}", self.market_name, e);
+                    self.report(|status| status.set_error(e.to_string()));
+                }
+            }
+        }
+    }
+
+    pub async fn prune(&self, latest_processed_block: i64) -> Result<(), Error> {
+        self.send(Operation::Prune(latest_processed_block)).await
+    }
+
+    /// Catches up the processing of blocks from the latest processed block to the latest block
+    /// from the source.
+    ///
+    /// The range is split into windows of `batch_size` blocks. Up to `concurrency` windows are
+    /// requested ahead at once, but they are handed to the dispatcher strictly in block order.
+    /// The events of a window are streamed rather than collected, so only the buffers of the
+    /// sources are held in memory for the windows ahead.
+    ///
+    /// A window is dispatched only once all its events are received. If the source fails
+    /// within a window, the error is returned without dispatching it, so the window is fetched
+    /// again from the latest processed block on restart.
+    ///
+    /// # Arguments
+    ///
+    /// * `latest_processed_block` - Latest processed block number.
+    /// * `to_block` - The block number until which to fetch historical events.
+    ///
+    /// # Returns
+    ///
+    /// Returns the block number of the latest processed block after catching up.
+    ///
+    pub async fn catch_up(
+        &self,
+        mut latest_processed_block: i64,
+        to_block: i64,
+    ) -> Result<i64, Error> {
+        let from_block = latest_processed_block;
+        let started_at = Instant::now();
+
+        let mut windows = stream::iter(windows(from_block, to_block, self.batch_size))
+            .map(|(window_from, window_to)| async move {
+                let events = self.source.fetch(window_from, window_to).await?;
+
+                Ok::<_, Error>((window_to, events))
+            })
+            .buffered(self.concurrency);
+
+        while let Some(window) = windows.next().await {
+            let (window_to, mut events) = window?;
+
+            while let Some(event) = events.next().await {
+                match event? {
+                    Ok(event) => {
+                        latest_processed_block = event.block_number;
+                        self.report(|status| status.set_last_event(event.block_timestamp));
+
+                        // Process event with collecting operations to dispatch
+                        self.handle_event(event).await?;
+                    }
+                    Err(rejection) => self.reject(rejection).await?,
+                }
+            }
+
+            // Dispatch operations
+            self.send(Operation::Dispatch(latest_processed_block))
+                .await?;
+
+            log::debug!(
+                "[{}] PROCESSED: {}",
+                self.market_name,
+                latest_processed_block
+            );
+            latest_processed_block = window_to;
+
+            log::info!(
+                "[{}] CATCH_UP: block {} of {}, ETA {}",
+                self.market_name,
+                window_to,
+                to_block,
+                format_eta(estimate_remaining(
+                    started_at.elapsed(),
+                    window_to - from_block,
+                    to_block - window_to,
+                ))
+            );
+        }
+
+        Ok(latest_processed_block)
+    }
+
+    /// Listens for new events and processes them in real-time.
+    ///
+    /// If the subscription fails or the stream of events ends or breaks, it logs the error and
+    /// attempts to resubscribe after a delay which grows according to the reconnect policy.
+    ///
+    /// Before the first event of a new block is handled, the latest blocks are verified against
+    /// the canonical chain. If they were reorganized, the market is rolled back to the fork
+    /// point and the events are requested again from there.
+    ///
+    /// # Arguments
+    ///
+    /// * `latest_processed_block` - The block number of the latest processed block.
+    ///
+    async fn listen_events(&self, mut latest_processed_block: i64) -> Result<(), Error> {
+        let mut backoff = self.reconnect.min_backoff();
+        self.report(|status| status.set_mode(IndexerMode::Live));
+        self.last_event_block
+            .store(latest_processed_block, Ordering::Relaxed);
+
+        let mut subscriptions = 0;
+
+        loop {
+            match self.source.subscribe(latest_processed_block + 1).await {
+                Ok(mut events) => {
+                    backoff = self.reconnect.min_backoff();
+                    subscriptions += 1;
+                    self.subscription.store(subscriptions, Ordering::Relaxed);
+                    self.report(|status| status.clear_error());
+
+                    while let Some(event) = events.next().await {
+                        let event = match event {
+                            Ok(event) => event,
+                            Err(e) => {
+                                log::error!("Error in the stream of new events: {e}");
+                                self.report(|status| status.set_error(e.to_string()));
+                                break;
+                            }
+                        };
+
+                        if let Ok(event) = &event {
+                            match self.find_fork(event.block_number).await {
+                                Ok(None) => {}
+                                Ok(Some(from_block)) => {
+                                    self.roll_back(from_block).await?;
+                                    latest_processed_block = from_block - 1;
+                                    self.last_event_block
+                                        .store(latest_processed_block, Ordering::Relaxed);
+                                    break;
+                                }
+                                Err(e) => {
+                                    log::error!("Failed to verify the latest blocks: {e}");
+                                    self.report(|status| status.set_error(e.to_string()));
+                                    break;
+                                }
+                            }
+                        }
+
+                        match event {
+                            Ok(event) => {
+                                latest_processed_block = event.block_number;
+                                self.last_event_block
+                                    .store(latest_processed_block, Ordering::Relaxed);
+                                self.report(|status| status.set_last_event(event.block_timestamp));
+
+                                log::debug!(
+                                    "[{}] LATEST_PROCESSED_BLOCK: {}",
+                                    self.market_name,
+                                    latest_processed_block
+                                );
+
+                                self.handle_event(event).await?;
+                            }
+                            Err(rejection) => self.reject(rejection).await?,
+                        }
+
+                        self.send(Operation::Dispatch(latest_processed_block))
+                            .await?;
+                    }
+                    self.subscription.store(0, Ordering::Relaxed);
+                }
+                Err(e) => {
+                    log::error!("Failed to subscribe to new events: {e}");
+                    self.report(|status| status.set_error(e.to_string()));
+                }
+            }
+
+            log::debug!("[{}] RECONNECTING...", self.market_name);
+            METRICS
+                .reconnects
+                .with_label_values(&[&self.market_name])
+                .inc();
+            self.report(|status| status.add_reconnect());
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(self.reconnect.max_backoff());
+        }
+    }
+
+    /// Finds the fork point of a reorganization before a block is handled.
+    ///
+    /// The latest blocks handed to the dispatcher are compared with the canonical chain from
+    /// the most recent one, until a block which is still on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_number` - The block number of the next event.
+    ///
+    /// # Returns
+    ///
+    /// Returns the first block which is no longer on the canonical chain, or `None` if the block
+    /// was already verified or the latest blocks are still canonical.
+    async fn find_fork(&self, block_number: i64) -> Result<Option<i64>, Error> {
+        let recent_blocks = self.recent_blocks.lock().unwrap().clone();
+        if recent_blocks
+            .back()
+            .is_none_or(|(last_block, _)| *last_block >= block_number)
+        {
+            return Ok(None);
+        }
+
+        let mut fork = None;
+        for (number, hash) in recent_blocks.iter().rev() {
+            match self.source.block_hash(*number).await? {
+                Some(canonical_hash) if !canonical_hash.eq_ignore_ascii_case(hash) => {
+                    fork = Some(*number);
+                }
+                _ => break,
+            }
+        }
+
+        // Every recent block was reorganized, the fork point may be even older
+        let oldest_block = recent_blocks.front().map(|(first_block, _)| *first_block);
+        if let Some(fork) = fork.filter(|fork| Some(*fork) == oldest_block) {
+            log::error!(
+                "[{}] REORG_BEYOND_RECENT_BLOCKS: the fork point may be before block {}",
+                self.market_name,
+                fork
+            );
+        }
+
+        Ok(fork)
+    }
+
+    /// Rolls the market back from the first block which is no longer on the canonical chain.
+    async fn roll_back(&self, from_block: i64) -> Result<(), Error> {
+        log::warn!(
+            "[{}] REORG_DETECTED: rolling back from block {}",
+            self.market_name,
+            from_block
+        );
+        self.recent_blocks
+            .lock()
+            .unwrap()
+            .retain(|(block, _)| *block < from_block);
+
+        self.send(Operation::Rollback(from_block)).await
+    }
+
+    /// Records a block handed to the dispatcher, only the latest ones are kept.
+    fn record_block(&self, block_number: i64, block_hash: &str) {
+        let mut recent_blocks = self.recent_blocks.lock().unwrap();
+        if recent_blocks
+            .back()
+            .is_some_and(|(last_block, _)| *last_block == block_number)
+        {
+            return;
+        }
+
+        recent_blocks.push_back((block_number, block_hash.to_string()));
+        if recent_blocks.len() > RECENT_BLOCKS {
+            recent_blocks.pop_front();
+        }
+    }
+
+    /// Records the latest block of the source.
+    fn report_chain_head(&self, block: i64) {
+        METRICS.set_chain_head(&self.market_name, block);
+        self.report(|status| status.set_chain_head(block));
+    }
+
+    /// Reports to the status of the market, if it's tracked.
+    fn report(&self, f: impl FnOnce(&StatusReporter)) {
+        if let Some(status) = &self.status {
+            f(status);
+        }
+    }
+
+    /// Handles an event by dispatching the appropriate operation.
+    ///
+    /// Every event is preceded by its block, which lets the dispatcher detect reorganizations,
+    /// and is journaled as it was received.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The `PangeaEvent` to be handled.
+    ///
+    /// # Errors
+    ///
+    /// Dead-letters the event if it can't be turned into an update. Fails if the dispatcher
+    /// has stopped.
+    pub async fn handle_event(&self, event: PangeaEvent) -> Result<(), Error> {
+        self.record_block(event.block_number, &event.block_hash);
+        self.send(Operation::Block(
+            event.block_number,
+            event.block_hash.clone(),
+        ))
+        .await?;
+
+        let event = Arc::new(event);
+        self.send(Operation::Journal(Arc::clone(&event))).await?;
+
+        let operation = match build_update(event) {
+            Ok(Some(update)) => Operation::Update(update),
+            Ok(None) => return Ok(()),
+            Err(rejection) => {
+                log::error!("[{}] {}", self.market_name, rejection.reason);
+                Operation::DeadLetter(rejection)
+            }
+        };
+        self.send(operation).await
+    }
+
+    /// Sends an operation to the dispatcher, waiting for room in the channel if it's full.
+    ///
+    /// The time spent waiting tells how far the dispatcher is behind the source. The depth of
+    /// the queue is recorded here as well, since the dispatcher doesn't receive anything while
+    /// it's stalled.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the dispatcher has stopped, so its operations can't be applied anymore.
+    async fn send(&self, operation: Operation) -> Result<(), Error> {
+        match self.operation_tx.try_send(operation) {
+            Ok(()) => {}
+            Err(TrySendError::Closed(_)) => {
+                return Err(Error::DispatcherStopped(self.market_name.clone()))
+            }
+            Err(TrySendError::Full(operation)) => {
+                let started_at = Instant::now();
+                self.operation_tx
+                    .send(operation)
+                    .await
+                    .map_err(|_| Error::DispatcherStopped(self.market_name.clone()))?;
+                METRICS
+                    .backpressure
+                    .with_label_values(&[&self.market_name])
+                    .inc_by(started_at.elapsed().as_secs_f64());
+            }
+        }
+
+        let queue_depth = self.operation_tx.max_capacity() - self.operation_tx.capacity();
+        METRICS
+            .queue_depth
+            .with_label_values(&[&self.market_name])
+            .set(queue_depth as i64);
+
+        Ok(())
+    }
+
+    /// Dead-letters an event which couldn't be decoded.
+    async fn reject(&self, rejection: Rejection) -> Result<(), Error> {
+        log::error!("[{}] {}", self.market_name, rejection.reason);
+        self.send(Operation::DeadLetter(rejection)).await
+    }
+}
+
+/// Builds the update of an event.
+///
+/// # Returns
+///
+/// Returns `None` for events without a type, which don't change the market, or a rejection
+/// if the event can't be turned into an update.
+pub fn build_update(event: Arc<PangeaEvent>) -> Result<Option<Update>, Rejection> {
+    let Some(event_type) = event.event_type.as_deref() else {
+        return Ok(None);
+    };
+
+    let kind = match event_type {
+        "Open" => UpdateKind::OpenOrder(event.build_order()?),
+        "Trade" => UpdateKind::Trade(event.build_trade()?),
+        "Cancel" => UpdateKind::CancelOrder(event.build_cancel()?),
+        _ => return Err(event.reject(format!("UNKNOWN_EVENT_TYPE: {}", event_type))),
+    };
+
+    Ok(Some(Update {
+        position: event.position(),
+        kind,
+        event,
+    }))
+}
+
+/// Splits the `[from_block, to_block]` range into consecutive windows of `batch_size` blocks.
+fn windows(from_block: i64, to_block: i64, batch_size: u64) -> Vec<(i64, i64)> {
+    let mut windows = Vec::new();
+    let mut window_from = from_block;
+    while window_from < to_block {
+        let window_to = (window_from + batch_size as i64).min(to_block);
+        windows.push((window_from, window_to));
+        window_from = window_to + 1;
+    }
+
+    windows
+}
+
+/// Estimates the time left to process the remaining blocks at the rate observed so far.
+fn estimate_remaining(elapsed: Duration, processed: i64, remaining: i64) -> Duration {
+    if processed <= 0 {
+        return Duration::ZERO;
+    }
+
+    elapsed.mul_f64(remaining.max(0) as f64 / processed as f64)
+}
+
+fn format_eta(eta: Duration) -> String {
+    let secs = eta.as_secs();
+    format!("{}h {:02}m {:02}s", secs / 3600, secs / 60 % 60, secs % 60)
+}
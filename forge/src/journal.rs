@@ -0,0 +1,107 @@
+use futures::{future::BoxFuture, stream, FutureExt, StreamExt};
+use sea_orm::DatabaseConnection;
+use sparker_core::repo;
+use std::sync::Arc;
+
+use crate::{
+    error::Error,
+    pangea::event::PangeaEvent,
+    source::{EventSource, EventStream},
+};
+
+/// Number of journaled events read from the database at once.
+const PAGE_SIZE: u64 = 10_000;
+
+/// Event source which replays the events journaled in the `raw_event` table.
+///
+/// The journal only holds the events which were already indexed, so the source is used to
+/// rebuild a market rather than to follow the chain.
+pub struct JournalSource {
+    db_conn: Arc<DatabaseConnection>,
+    market_id: String,
+}
+
+impl JournalSource {
+    pub fn new(db_conn: Arc<DatabaseConnection>, market_id: &str) -> Self {
+        Self {
+            db_conn,
+            market_id: market_id.to_string(),
+        }
+    }
+
+    /// Reads the journaled events of the `[from_block, to_block]` range page by page.
+    ///
+    /// A database error is yielded and ends the stream.
+    fn read_events(&self, from_block: i64, to_block: i64) -> EventStream {
+        let db_conn = Arc::clone(&self.db_conn);
+        let market_id = self.market_id.clone();
+
+        stream::unfold(Some(0), move |offset| {
+            let db_conn = Arc::clone(&db_conn);
+            let market_id = market_id.clone();
+
+            async move {
+                let offset = offset?;
+                let raw_events = repo::raw_event::Query::find_range(
+                    db_conn.as_ref(),
+                    &market_id,
+                    from_block,
+                    to_block,
+                    PAGE_SIZE,
+                    offset,
+                )
+                .await;
+
+                let raw_events = match raw_events {
+                    Ok(raw_events) => raw_events,
+                    Err(e) => {
+                        log::error!("Error while reading the journal: {e}");
+                        return Some((stream::iter(vec![Err(e.into())]), None));
+                    }
+                };
+
+                let next = (raw_events.len() as u64 == PAGE_SIZE).then_some(offset + PAGE_SIZE);
+                let events = raw_events
+                    .into_iter()
+                    .map(|raw_event| Ok(PangeaEvent::from_json(&raw_event.payload)))
+                    .collect::<Vec<_>>();
+
+                Some((stream::iter(events), next))
+            }
+        })
+        .flatten()
+        .boxed()
+    }
+}
+
+impl EventSource for JournalSource {
+    fn latest_block(&self) -> BoxFuture<'_, Result<i64, Error>> {
+        async move {
+            let latest =
+                repo::raw_event::Query::find_latest_block(self.db_conn.as_ref(), &self.market_id)
+                    .await?;
+
+            Ok(latest.unwrap_or_default())
+        }
+        .boxed()
+    }
+
+    fn block_hash(&self, _block_number: i64) -> BoxFuture<'_, Result<Option<String>, Error>> {
+        // The journal is only replayed, the chain it was read from isn't known
+        async move { Ok(None) }.boxed()
+    }
+
+    fn fetch(&self, from_block: i64, to_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move { Ok(self.read_events(from_block, to_block)) }.boxed()
+    }
+
+    fn subscribe(&self, from_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move {
+            // Nothing is journaled without being indexed, so the tail just stays quiet
+            let events = self.read_events(from_block, i64::MAX);
+
+            Ok(events.chain(stream::pending()).boxed())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,98 @@
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, Statement,
+    TransactionTrait,
+};
+use tokio::time::{interval, sleep, Duration};
+
+use crate::{db, error::Error};
+
+/// Interval between the attempts to acquire a lease which is held by another instance.
+const ACQUIRE_INTERVAL: Duration = Duration::from_secs(10);
+/// Interval between the checks that the connection holding a lease is still alive.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Exclusive right of an instance to index a market.
+///
+/// The lease is a Postgres advisory lock held by a transaction which stays open for the
+/// lifetime of the lease, so the lock is bound to a single connection and is released as
+/// soon as the lease is dropped or the connection is lost. The connection is dedicated to
+/// the lease, it's never taken from the pool of the indexer.
+pub struct MarketLease {
+    market_id: String,
+    txn: DatabaseTransaction,
+    _db_conn: DatabaseConnection,
+}
+
+impl MarketLease {
+    /// Tries to acquire the lease of a market.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the lease is held by another instance.
+    pub async fn acquire(database_url: &str, market_id: &str) -> Result<Option<Self>, Error> {
+        let db_conn = db::build_dedicated_connection(database_url).await?;
+        let txn = db_conn.begin().await?;
+        let row = txn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Postgres,
+                "SELECT pg_try_advisory_xact_lock(hashtextextended($1, 0)) AS locked",
+                [lock_key(market_id).into()],
+            ))
+            .await?;
+        let locked = match row {
+            Some(row) => row.try_get::<bool>("", "locked")?,
+            None => false,
+        };
+
+        if !locked {
+            txn.rollback().await?;
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            market_id: market_id.to_string(),
+            txn,
+            _db_conn: db_conn,
+        }))
+    }
+
+    /// Waits until the lease of a market is released by the instance holding it.
+    pub async fn wait(database_url: &str, market_id: &str) -> Result<Self, Error> {
+        loop {
+            if let Some(lease) = Self::acquire(database_url, market_id).await? {
+                return Ok(lease);
+            }
+
+            log::info!("[{}] LEASE_HELD: waiting for another instance", market_id);
+            sleep(ACQUIRE_INTERVAL).await;
+        }
+    }
+
+    /// Checks periodically that the lease is still held.
+    ///
+    /// # Errors
+    ///
+    /// Fails once the connection holding the lease is lost, from that moment another instance
+    /// may acquire it.
+    pub async fn keep_alive(&self) -> Result<(), Error> {
+        let mut heartbeat = interval(HEARTBEAT_INTERVAL);
+
+        loop {
+            heartbeat.tick().await;
+
+            if let Err(e) = self
+                .txn
+                .execute(Statement::from_string(DbBackend::Postgres, "SELECT 1"))
+                .await
+            {
+                log::error!("[{}] LEASE_LOST: {}", self.market_id, e);
+                return Err(Error::LeaseLost(self.market_id.clone()));
+            }
+        }
+    }
+}
+
+/// Key of the advisory lock of a market.
+fn lock_key(market_id: &str) -> String {
+    format!("forge:{}", market_id.to_lowercase())
+}
@@ -1,78 +1,281 @@
-use dispatcher::Operation;
+use clap::Parser;
 use dotenv::dotenv;
 use error::Error;
+use sea_orm::DatabaseConnection;
+use source::EventSource;
 use sparker_core::repo::state;
-use std::sync::Arc;
+use std::{process, sync::Arc};
 use tokio::{
     signal::unix::{signal, SignalKind},
-    sync::{mpsc::unbounded_channel, Mutex},
+    sync::{mpsc::unbounded_channel, watch},
+    task::JoinSet,
+    time::timeout,
 };
 
-use crate::{config::Config, dispatcher::OperationDispatcher, pangea::PangeaIndexer};
+use crate::{
+    config::{CacheCommand, Cli, Command, Config, MarketInfo, SourceKind},
+    dispatcher::{operation_channel, OperationDispatcher},
+    fuel::FuelSource,
+    indexer::Indexer,
+    lease::MarketLease,
+    pangea::{cache::BatchCache, PangeaSource},
+    replay::ReplaySource,
+    status::StatusReporter,
+    types::{stopped, StopSignal},
+};
 
 mod config;
 mod db;
 mod dispatcher;
 mod error;
+mod fuel;
+mod indexer;
+mod journal;
+mod lease;
+mod metrics;
 mod pangea;
+mod rebuild;
+mod redrive;
+mod registry;
+mod replay;
+mod source;
+mod status;
 mod types;
 
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() {
     dotenv().ok();
     env_logger::init();
 
-    let config = Config::load("config.mainnet.json")?;
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        log::error!("{}", e);
+        process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), Error> {
+    let config = Arc::new(Config::build(&cli)?);
+
+    // The cache is managed without the database
+    if let Some(Command::Cache { command }) = cli.command {
+        return manage_cache(&config, command).await;
+    }
 
-    let db_conn = db::build_connection().await?;
+    let db_conn = db::build_connection(config.database_url()).await?;
     let db_conn = Arc::new(db_conn);
 
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run | Command::Cache { .. } => {}
+        Command::Redrive => {
+            for market in config.markets.iter() {
+                let _lease = acquire_lease(&config, market).await?;
+                redrive::redrive(Arc::clone(&db_conn), market).await?;
+            }
+
+            return Ok(());
+        }
+        Command::Rebuild => {
+            for market in config.markets.iter() {
+                let _lease = acquire_lease(&config, market).await?;
+                rebuild::rebuild(Arc::clone(&db_conn), &config, market).await?;
+            }
+
+            return Ok(());
+        }
+    }
+
     // ------------------ Start indexers ------------------
     log::info!("Starting indexers...");
-    for market in config.markets {
-        // -------------- Start operation dispatcher --------------
-        let (operation_tx, operation_rx) = unbounded_channel::<Operation>();
-        let operation_tx = Arc::new(operation_tx);
-        let operation_rx = Arc::new(Mutex::new(operation_rx));
-
-        let operation_dispatcher = OperationDispatcher::new(
-            market.id.clone(),
-            Arc::clone(&db_conn),
-            Arc::clone(&operation_rx),
-        );
-        tokio::spawn(async move {
-            operation_dispatcher.start().await;
-        });
+    let (failure_tx, mut failure_rx) = unbounded_channel::<Error>();
+
+    let metrics_addr = config.metrics_addr;
+    let stall_timeout = config.stall_timeout();
+    let metrics_failure_tx = failure_tx.clone();
+    tokio::spawn(async move {
+        if let Err(e) = metrics::serve(metrics_addr, stall_timeout).await {
+            let _ = metrics_failure_tx.send(e);
+        }
+    });
+
+    let (stop_tx, stop_rx) = watch::channel(false);
+    let mut markets = JoinSet::new();
+    for market in config.markets.iter().cloned() {
+        let db_conn = Arc::clone(&db_conn);
+        let config = Arc::clone(&config);
+        let failure_tx = failure_tx.clone();
+        let stop = stop_rx.clone();
 
-        let indexer = PangeaIndexer::create(
-            &config.pangea_host,
-            &market.id,
-            &market.name,
-            Arc::clone(&operation_tx),
-        )
-        .await?;
-
-        // Get the latest processed block from the database
-        let latest_processed_block =
-            state::Query::find_latest_processed_block(&db_conn, &market.id)
-                .await?
-                .unwrap_or(config.pangea_start_block);
-
-        tokio::spawn(async move {
-            if let Err(e) = indexer.start(latest_processed_block).await {
-                log::error!("Error while running indexer: {}", e);
+        markets.spawn(async move {
+            if let Err(e) = run_market(db_conn, config, market, stop).await {
+                let _ = failure_tx.send(e);
             }
         });
     }
     // ---------------------------------------------------
 
     let mut sigint = signal(SignalKind::interrupt()).unwrap();
-    let mut sigterm = signal(SignalKind::interrupt()).unwrap();
+    let mut sigterm = signal(SignalKind::terminate()).unwrap();
+
+    let result = tokio::select! {
+        _ = sigint.recv() => {
+            log::info!("Received signal SIGINT. Shutting down.");
+            Ok(())
+        }
+        _ = sigterm.recv() => {
+            log::info!("Received signal SIGTERM. Shutting down.");
+            Ok(())
+        }
+        Some(e) = failure_rx.recv() => Err(e),
+    };
+
+    // The markets stop fetching events and commit the blocks they already have
+    stop_tx.send_replace(true);
+    let drain = async { while markets.join_next().await.is_some() {} };
+    let shutdown_timeout = config.shutdown_timeout();
+    match timeout(shutdown_timeout, drain).await {
+        Ok(()) => log::info!("SHUTDOWN_COMPLETE"),
+        Err(_) => log::error!(
+            "SHUTDOWN_TIMEOUT: markets still running after {:?}",
+            shutdown_timeout
+        ),
+    }
+
+    result
+}
+
+/// Indexes a market once its lease is acquired.
+///
+/// The market is indexed for as long as the lease is held, losing it is an error since the
+/// market may be taken over by another instance. Its tasks are aborted then, before the error
+/// is returned.
+///
+/// Once stopped, the indexer stops fetching events and the dispatcher commits the blocks which
+/// were fully received. The lease is held until then, so the next holder resumes from the
+/// final checkpoint.
+async fn run_market(
+    db_conn: Arc<DatabaseConnection>,
+    config: Arc<Config>,
+    market: MarketInfo,
+    mut stop: StopSignal,
+) -> Result<(), Error> {
+    let lease = tokio::select! {
+        lease = MarketLease::wait(config.database_url(), &market.id) => lease?,
+        _ = stopped(&mut stop) => return Ok(()),
+    };
+    log::info!("[{}] LEASE_ACQUIRED", market.name);
+
+    // The market is indexed even if its metadata can't be read
+    if let Err(e) = registry::register(&db_conn, &config, &market).await {
+        log::error!("[{}] MARKET_METADATA_UNKNOWN: {}", market.name, e);
+    }
+
+    // -------------- Start operation dispatcher --------------
+    let (operation_tx, operation_rx) = operation_channel();
+
+    let operation_dispatcher = OperationDispatcher::new(
+        market.id.clone(),
+        market.name.clone(),
+        Arc::clone(&db_conn),
+        operation_rx,
+        true,
+    );
+    // The dispatcher stops once the indexer is done and its operations are processed
+    let mut dispatcher_handle = tokio::spawn(async move {
+        operation_dispatcher.start().await;
+    });
+
+    let status = Arc::new(StatusReporter::new(&market.id, Arc::clone(&db_conn)));
+    let status_reporter = Arc::clone(&status);
+    let status_stop = stop.clone();
+    let status_handle = tokio::spawn(async move {
+        status_reporter.start(status_stop).await;
+    });
+
+    let source: Box<dyn EventSource> = match (&config.replay_file, config.source) {
+        (Some(path), _) => Box::new(ReplaySource::new(path, &market.id)),
+        (None, SourceKind::Pangea) => Box::new(PangeaSource::create(&config, &market.id).await?),
+        (None, SourceKind::Fuel) => Box::new(FuelSource::create(&config, &market.id).await?),
+    };
+    let indexer = Indexer::new(
+        source,
+        &market.name,
+        operation_tx,
+        config.batch_size(&market),
+        config.catch_up_concurrency,
+        config.reconnect,
+        Some(Arc::clone(&status)),
+    );
+
+    // Get the latest processed block from the database, once the previous holder of the
+    // lease is done with it
+    let latest_processed_block = state::Query::find_latest_processed_block(&db_conn, &market.id)
+        .await?
+        .unwrap_or(config.start_block(&market));
+
+    let indexer_handle = tokio::spawn(async move {
+        let result = indexer.start(latest_processed_block, stop).await;
+        if let Err(e) = &result {
+            log::error!("Error while running indexer: {}", e);
+            status.set_error(e.to_string());
+        }
+
+        result
+    });
 
     tokio::select! {
-        _ = sigint.recv() => log::info!("Received signal SIGINT. Shutting down."),
-        _ = sigterm.recv() => log::info!("Received signal SIGTERM. Shutting down."),
+        result = lease.keep_alive() => {
+            // Another instance may hold the lease by now, nothing is written past this point.
+            // An aborted dispatcher rolls back the transaction it was in
+            indexer_handle.abort();
+            dispatcher_handle.abort();
+            status_handle.abort();
+            let _ = tokio::join!(indexer_handle, dispatcher_handle, status_handle);
+            log::info!("[{}] MARKET_ABORTED", market.name);
+
+            return result;
+        }
+        _ = &mut dispatcher_handle => {}
+    }
+    log::info!("[{}] DISPATCHER_DRAINED", market.name);
+
+    // The indexer is done once the dispatcher is, since it holds the sender of the operations
+    indexer_handle.await?
+}
+
+/// Lists or evicts the cached Pangea batches of the markets.
+async fn manage_cache(config: &Config, command: CacheCommand) -> Result<(), Error> {
+    let cache_dir = config.cache_dir.as_ref().ok_or_else(|| {
+        Error::Config("Cache directory is not set, use --cache-dir or CACHE_DIR".to_string())
+    })?;
+    let cache = BatchCache::new(cache_dir);
+
+    for market in config.markets.iter() {
+        match command {
+            CacheCommand::List => {
+                let batches = cache.list(&market.id).await?;
+                let size = batches.iter().map(|batch| batch.size).sum::<u64>();
+                println!("{}: {} batches, {} bytes", market.name, batches.len(), size);
+                for batch in batches {
+                    println!(
+                        "  {}-{}: {} bytes",
+                        batch.from_block, batch.to_block, batch.size
+                    );
+                }
+            }
+            CacheCommand::Evict { from_block } => {
+                let evicted = cache.evict(&market.id, from_block).await?;
+                println!("{}: {} batches evicted", market.name, evicted);
+            }
+        }
     }
 
     Ok(())
 }
+
+/// Acquires the lease of a market which mustn't be indexed meanwhile.
+async fn acquire_lease(config: &Config, market: &MarketInfo) -> Result<MarketLease, Error> {
+    MarketLease::acquire(config.database_url(), &market.id)
+        .await?
+        .ok_or_else(|| Error::MarketLocked(market.name.clone()))
+}
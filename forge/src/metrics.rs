@@ -0,0 +1,284 @@
+use axum::{http::StatusCode, routing::get, Router};
+use chrono::Utc;
+use prometheus::{
+    CounterVec, Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::error::Error;
+
+/// Metrics of the indexers, labeled by market name.
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    /// Latest block stored as processed.
+    pub latest_processed_block: IntGaugeVec,
+    /// Latest block known to the source.
+    pub chain_head: IntGaugeVec,
+    /// Number of blocks between the latest processed block and the chain head.
+    pub lag_blocks: IntGaugeVec,
+    /// Seconds since the time of the latest processed block, refreshed on every scrape.
+    pub lag_seconds: IntGaugeVec,
+    /// Latest block reached by market while no events were received, which is processed
+    /// even though it isn't stored as such.
+    idle_blocks: Mutex<HashMap<String, i64>>,
+    /// Time of the latest processed block by market: the block time of its latest event, or
+    /// the time the chain head was seen at it while no events were received.
+    processed_timestamps: Mutex<HashMap<String, i64>>,
+    /// Start of the operation each dispatcher is running by market.
+    running_operations: Mutex<HashMap<String, Instant>>,
+    pub events: IntCounterVec,
+    pub dispatch_duration: HistogramVec,
+    pub db_errors: IntCounterVec,
+    pub reconnects: IntCounterVec,
+    /// Number of operations waiting for the dispatcher.
+    pub queue_depth: IntGaugeVec,
+    /// Time the indexer waited for room in the channel of the dispatcher.
+    pub backpressure: CounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("forge".to_string()), None).unwrap();
+
+        let metrics = Self {
+            registry,
+            latest_processed_block: IntGaugeVec::new(
+                Opts::new("latest_processed_block", "Latest processed block"),
+                &["market"],
+            )
+            .unwrap(),
+            chain_head: IntGaugeVec::new(
+                Opts::new("chain_head", "Latest block known to the source"),
+                &["market"],
+            )
+            .unwrap(),
+            lag_blocks: IntGaugeVec::new(
+                Opts::new(
+                    "lag_blocks",
+                    "Blocks between the latest processed block and the chain head",
+                ),
+                &["market"],
+            )
+            .unwrap(),
+            lag_seconds: IntGaugeVec::new(
+                Opts::new(
+                    "lag_seconds",
+                    "Seconds since the time of the latest processed block",
+                ),
+                &["market"],
+            )
+            .unwrap(),
+            events: IntCounterVec::new(
+                Opts::new("events_total", "Events processed by type"),
+                &["market", "event_type"],
+            )
+            .unwrap(),
+            dispatch_duration: HistogramVec::new(
+                HistogramOpts::new("dispatch_duration_seconds", "Duration of the dispatches"),
+                &["market"],
+            )
+            .unwrap(),
+            db_errors: IntCounterVec::new(
+                Opts::new("db_errors_total", "Failed database operations"),
+                &["market", "operation"],
+            )
+            .unwrap(),
+            reconnects: IntCounterVec::new(
+                Opts::new("reconnects_total", "Reconnections to the source"),
+                &["market"],
+            )
+            .unwrap(),
+            queue_depth: IntGaugeVec::new(
+                Opts::new("queue_depth", "Operations waiting for the dispatcher"),
+                &["market"],
+            )
+            .unwrap(),
+            backpressure: CounterVec::new(
+                Opts::new(
+                    "backpressure_seconds_total",
+                    "Time spent waiting for the dispatcher to make room for operations",
+                ),
+                &["market"],
+            )
+            .unwrap(),
+            idle_blocks: Mutex::new(HashMap::new()),
+            processed_timestamps: Mutex::new(HashMap::new()),
+            running_operations: Mutex::new(HashMap::new()),
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 10] = [
+            Box::new(metrics.latest_processed_block.clone()),
+            Box::new(metrics.chain_head.clone()),
+            Box::new(metrics.lag_blocks.clone()),
+            Box::new(metrics.lag_seconds.clone()),
+            Box::new(metrics.events.clone()),
+            Box::new(metrics.dispatch_duration.clone()),
+            Box::new(metrics.db_errors.clone()),
+            Box::new(metrics.reconnects.clone()),
+            Box::new(metrics.queue_depth.clone()),
+            Box::new(metrics.backpressure.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).unwrap();
+        }
+
+        metrics
+    }
+
+    /// Records the latest processed block of a market.
+    pub fn set_latest_processed_block(&self, market: &str, block: i64) {
+        self.latest_processed_block
+            .with_label_values(&[market])
+            .set(block);
+        self.update_lag(market);
+    }
+
+    /// Records the latest block known to the source of a market.
+    pub fn set_chain_head(&self, market: &str, block: i64) {
+        self.chain_head.with_label_values(&[market]).set(block);
+        self.update_lag(market);
+    }
+
+    /// Records the block timestamp of the latest processed event of a market.
+    pub fn set_last_event_timestamp(&self, market: &str, timestamp: i64) {
+        self.set_processed_timestamp(market, timestamp);
+    }
+
+    /// Records a block reached by a market while no events were received.
+    ///
+    /// # Arguments
+    ///
+    /// * `market` - The market name.
+    /// * `block` - The chain head the market caught up with.
+    /// * `timestamp` - The time the chain head was seen at the block.
+    ///
+    pub fn set_idle_block(&self, market: &str, block: i64, timestamp: i64) {
+        self.idle_blocks
+            .lock()
+            .unwrap()
+            .entry(market.to_string())
+            .and_modify(|idle_block| *idle_block = (*idle_block).max(block))
+            .or_insert(block);
+        self.set_processed_timestamp(market, timestamp);
+        self.update_lag(market);
+    }
+
+    fn set_processed_timestamp(&self, market: &str, timestamp: i64) {
+        self.processed_timestamps
+            .lock()
+            .unwrap()
+            .entry(market.to_string())
+            .and_modify(|processed_timestamp| {
+                *processed_timestamp = (*processed_timestamp).max(timestamp)
+            })
+            .or_insert(timestamp);
+    }
+
+    fn update_lag(&self, market: &str) {
+        let chain_head = self.chain_head.with_label_values(&[market]).get();
+        let latest_processed_block = self
+            .latest_processed_block
+            .with_label_values(&[market])
+            .get();
+        let idle_block = self.idle_blocks.lock().unwrap().get(market).copied();
+        let processed_block = idle_block.map_or(latest_processed_block, |idle_block| {
+            idle_block.max(latest_processed_block)
+        });
+
+        self.lag_blocks
+            .with_label_values(&[market])
+            .set((chain_head - processed_block).max(0));
+    }
+
+    /// Records the start of an operation of the dispatcher of a market.
+    pub fn start_operation(&self, market: &str) {
+        self.running_operations
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), Instant::now());
+    }
+
+    /// Records the end of the running operation of the dispatcher of a market.
+    pub fn finish_operation(&self, market: &str) {
+        self.running_operations.lock().unwrap().remove(market);
+    }
+
+    /// Returns the markets whose dispatcher has been running the same operation for longer
+    /// than the timeout, e.g. retrying a transaction the database keeps rejecting.
+    fn stalled_markets(&self, timeout: Duration) -> Vec<String> {
+        self.running_operations
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, started_at)| started_at.elapsed() > timeout)
+            .map(|(market, _)| market.clone())
+            .collect()
+    }
+
+    /// Encodes the metrics in the Prometheus text format.
+    fn encode(&self) -> Result<String, prometheus::Error> {
+        // The lag in seconds grows with time, not with the blocks
+        let now = Utc::now().timestamp();
+        for (market, timestamp) in self.processed_timestamps.lock().unwrap().iter() {
+            let lag_seconds = match self.lag_blocks.with_label_values(&[market]).get() {
+                0 => 0,
+                _ => (now - timestamp).max(0),
+            };
+            self.lag_seconds
+                .with_label_values(&[market])
+                .set(lag_seconds);
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+/// Serves the metrics on `/metrics` and the liveness of forge on `/health`.
+///
+/// # Arguments
+///
+/// * `addr` - The address to listen on.
+/// * `stall_timeout` - The time after which a dispatcher which doesn't make progress makes
+///   forge unhealthy.
+///
+pub async fn serve(addr: SocketAddr, stall_timeout: Duration) -> Result<(), Error> {
+    let app = Router::new()
+        .route("/metrics", get(metrics))
+        .route("/health", get(move || health(stall_timeout)));
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    log::info!("Serving metrics on {}", addr);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+/// Reports forge as unavailable while a dispatcher is stalled, so it's restarted from the
+/// latest processed block.
+async fn health(stall_timeout: Duration) -> StatusCode {
+    let stalled_markets = METRICS.stalled_markets(stall_timeout);
+    if stalled_markets.is_empty() {
+        return StatusCode::OK;
+    }
+
+    log::warn!("DISPATCHER_STALLED: {}", stalled_markets.join(", "));
+    StatusCode::SERVICE_UNAVAILABLE
+}
+
+async fn metrics() -> Result<String, StatusCode> {
+    METRICS.encode().map_err(|e| {
+        log::error!("Failed to encode metrics: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
@@ -0,0 +1,181 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tokio::task::spawn_blocking;
+
+use crate::error::Error;
+
+/// Extension of the cached batches.
+const EXTENSION: &str = "jsonl.gz";
+
+/// On-disk cache of the raw batches of events fetched from Pangea.
+///
+/// Every batch is the `JsonStream` payload of a market and a block range, one event per line,
+/// gzip-compressed in `<dir>/<market id>/<from block>-<to block>.jsonl.gz`. Only complete
+/// batches are cached, a batch is written to a temporary file first and then renamed.
+#[derive(Debug, Clone)]
+pub struct BatchCache {
+    dir: PathBuf,
+}
+
+/// A batch of a market stored in the cache.
+#[derive(Debug, Clone)]
+pub struct CachedBatch {
+    pub from_block: i64,
+    pub to_block: i64,
+    /// Compressed size in bytes.
+    pub size: u64,
+    pub path: PathBuf,
+}
+
+impl BatchCache {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn market_dir(&self, market_id: &str) -> PathBuf {
+        self.dir.join(market_id.to_lowercase())
+    }
+
+    fn batch_path(&self, market_id: &str, from_block: i64, to_block: i64) -> PathBuf {
+        self.market_dir(market_id)
+            .join(format!("{}-{}.{}", from_block, to_block, EXTENSION))
+    }
+
+    /// Reads a cached batch.
+    ///
+    /// # Returns
+    ///
+    /// Returns the raw events of the batch, or `None` if it isn't cached. A batch which can't
+    /// be read is evicted and treated as missing.
+    pub async fn read(
+        &self,
+        market_id: &str,
+        from_block: i64,
+        to_block: i64,
+    ) -> Option<Vec<Vec<u8>>> {
+        let path = self.batch_path(market_id, from_block, to_block);
+
+        spawn_blocking(move || {
+            if !path.exists() {
+                return None;
+            }
+
+            let read = || -> std::io::Result<Vec<Vec<u8>>> {
+                let reader = BufReader::new(GzDecoder::new(File::open(&path)?));
+                reader
+                    .split(b'\n')
+                    .filter(|line| !line.as_ref().is_ok_and(|line| line.is_empty()))
+                    .collect()
+            };
+
+            match read() {
+                Ok(events) => Some(events),
+                Err(e) => {
+                    log::warn!("CACHE_CORRUPTED: {}: {}", path.display(), e);
+                    let _ = fs::remove_file(&path);
+                    None
+                }
+            }
+        })
+        .await
+        .ok()
+        .flatten()
+    }
+
+    /// Stores a complete batch.
+    pub async fn write(
+        &self,
+        market_id: &str,
+        from_block: i64,
+        to_block: i64,
+        events: Vec<Vec<u8>>,
+    ) -> Result<(), Error> {
+        let path = self.batch_path(market_id, from_block, to_block);
+
+        spawn_blocking(move || -> Result<(), Error> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            let tmp_path = path.with_extension("tmp");
+            let mut writer = GzEncoder::new(
+                BufWriter::new(File::create(&tmp_path)?),
+                Compression::default(),
+            );
+            for event in events {
+                writer.write_all(&event)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.finish()?.flush()?;
+            fs::rename(&tmp_path, &path)?;
+
+            Ok(())
+        })
+        .await
+        .map_err(|e| Error::IO(e.into()))?
+    }
+
+    /// Lists the cached batches of a market, sorted by block.
+    pub async fn list(&self, market_id: &str) -> Result<Vec<CachedBatch>, Error> {
+        let market_dir = self.market_dir(market_id);
+
+        spawn_blocking(move || -> Result<Vec<CachedBatch>, Error> {
+            let mut batches = Vec::new();
+            if !market_dir.exists() {
+                return Ok(batches);
+            }
+
+            for entry in fs::read_dir(&market_dir)? {
+                let entry = entry?;
+                let Some((from_block, to_block)) = parse_batch_name(&entry.path()) else {
+                    continue;
+                };
+
+                batches.push(CachedBatch {
+                    from_block,
+                    to_block,
+                    size: entry.metadata()?.len(),
+                    path: entry.path(),
+                });
+            }
+            batches.sort_by_key(|batch| batch.from_block);
+
+            Ok(batches)
+        })
+        .await
+        .map_err(|e| Error::IO(e.into()))?
+    }
+
+    /// Evicts the cached batches of a market which end at or after the given block.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of evicted batches.
+    pub async fn evict(&self, market_id: &str, from_block: i64) -> Result<usize, Error> {
+        let batches = self.list(market_id).await?;
+
+        let mut evicted = 0;
+        for batch in batches.iter().filter(|batch| batch.to_block >= from_block) {
+            tokio::fs::remove_file(&batch.path).await?;
+            evicted += 1;
+        }
+
+        Ok(evicted)
+    }
+}
+
+/// Parses the block range of a cached batch from its file name.
+fn parse_batch_name(path: &Path) -> Option<(i64, i64)> {
+    let name = path.file_name()?.to_str()?;
+    let (from_block, to_block) = name
+        .strip_suffix(&format!(".{}", EXTENSION))?
+        .split_once('-')?;
+
+    Some((from_block.parse().ok()?, to_block.parse().ok()?))
+}
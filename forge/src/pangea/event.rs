@@ -1,8 +1,14 @@
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDateTime};
 use ethers_core::k256::sha2::{Digest, Sha256};
 use rustc_hex::ToHex;
 use serde::{Deserialize, Serialize};
-use sparker_core::{LimitType, Order, OrderStatus, OrderType, Trade};
+use sparker_core::{LimitType, Order, OrderStatus, OrderType, RawEvent, Trade, U128, U256};
+
+use crate::types::{Cancellation, Position, Rejection};
+
+/// Reason of the events which can't be decoded. They aren't journaled, so the dead letter is
+/// the only record of them.
+pub const INVALID_EVENT: &str = "INVALID_EVENT";
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PangeaEvent {
@@ -28,6 +34,52 @@ pub struct PangeaEvent {
 }
 
 impl PangeaEvent {
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, Rejection> {
+        match String::from_utf8(data) {
+            Ok(data) => Self::from_json(&data),
+            Err(e) => Err(Rejection {
+                block_number: None,
+                payload: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                reason: format!("{}: {}", INVALID_EVENT, e),
+            }),
+        }
+    }
+
+    /// Decodes an event from its JSON representation.
+    ///
+    /// An event which can't be decoded is rejected with its raw payload, along with its block
+    /// if the payload has one.
+    pub fn from_json(data: &str) -> Result<Self, Rejection> {
+        serde_json::from_str::<PangeaEvent>(data).map_err(|e| {
+            let block_number = serde_json::from_str::<serde_json::Value>(data)
+                .ok()
+                .and_then(|value| value.get("block_number")?.as_i64());
+
+            Rejection {
+                block_number,
+                payload: data.to_string(),
+                reason: format!("{}: {}", INVALID_EVENT, e),
+            }
+        })
+    }
+
+    /// Rejects the event with the given reason.
+    pub fn reject(&self, reason: String) -> Rejection {
+        Rejection {
+            block_number: Some(self.block_number),
+            payload: serde_json::to_string(self).unwrap_or_default(),
+            reason,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        Position {
+            block_number: self.block_number,
+            transaction_index: self.transaction_index,
+            log_index: self.log_index,
+        }
+    }
+
     pub fn order_type(&self) -> Option<OrderType> {
         self.order_type
             .as_deref()
@@ -47,15 +99,12 @@ impl PangeaEvent {
         }
     }
 
-    pub fn trade_id(&self) -> String {
+    /// Derives the id of the trade of the event, which has the given amount.
+    fn trade_id(&self, amount: u128) -> String {
         let hex: String = Sha256::digest(
             format!(
                 "{}{}{}{}{}",
-                self.transaction_hash,
-                self.order_id,
-                self.block_timestamp,
-                self.amount.unwrap(),
-                self.log_index,
+                self.transaction_hash, self.order_id, self.block_timestamp, amount, self.log_index,
             )
             .as_bytes(),
         )
@@ -64,48 +113,186 @@ impl PangeaEvent {
         format!("0x{}", hex)
     }
 
-    pub fn build_order(&self) -> Option<Order> {
-        if let (Some(price), Some(amount), Some(user)) = (self.price, self.amount, &self.user) {
-            let order_type = self.order_type().unwrap();
+    /// Returns the time of the block of the event.
+    ///
+    /// # Errors
+    ///
+    /// Rejects the event if its timestamp is out of range.
+    fn timestamp(&self) -> Result<NaiveDateTime, Rejection> {
+        DateTime::from_timestamp(self.block_timestamp, 0)
+            .map(|timestamp| timestamp.naive_utc())
+            .ok_or_else(|| {
+                self.reject(format!(
+                    "INVALID_TIMESTAMP: {} is out of range",
+                    self.block_timestamp
+                ))
+            })
+    }
 
-            Some(Order {
+    /// Builds the order opened by the event.
+    ///
+    /// # Errors
+    ///
+    /// Rejects the event if a field of the order is missing or invalid.
+    pub fn build_order(&self) -> Result<Order, Rejection> {
+        if let (Some(price), Some(amount), Some(user), Some(order_type), Some(asset)) = (
+            self.price,
+            self.amount,
+            &self.user,
+            self.order_type(),
+            &self.asset,
+        ) {
+            Ok(Order {
                 tx_id: self.transaction_hash.clone(),
                 order_id: self.order_id.clone(),
                 order_type,
                 user: user.to_owned(),
-                asset: self.asset.as_deref().unwrap().to_owned(),
-                amount: amount as u64,
-                price: price as u64,
+                asset: asset.to_owned(),
+                initial_amount: U128(amount),
+                filled_amount: U128::ZERO,
+                remaining_amount: U128(amount),
+                price: U128(price),
+                average_fill_price: U128::ZERO,
+                fill_volume: U256::ZERO,
                 status: OrderStatus::New,
                 block_number: self.block_number as u64,
-                timestamp: DateTime::from_timestamp(self.block_timestamp, 0)
-                    .unwrap()
-                    .naive_utc(),
+                timestamp: self.timestamp()?,
                 market_id: self.market_id.clone(),
+                decimals: None,
             })
         } else {
-            None
+            Err(self.reject("INVALID_ORDER: missing order fields".to_string()))
         }
     }
 
-    pub fn build_trade(&self) -> Option<Trade> {
-        if let (Some(price), Some(amount)) = (self.price, self.amount) {
-            Some(Trade {
+    /// Builds the trade of the event.
+    ///
+    /// # Errors
+    ///
+    /// Rejects the event if a field of the trade is missing or invalid.
+    pub fn build_trade(&self) -> Result<Trade, Rejection> {
+        if let (Some(price), Some(amount), Some(user)) = (self.price, self.amount, &self.user) {
+            Ok(Trade {
                 tx_id: self.transaction_hash.clone(),
-                trade_id: self.trade_id(),
+                trade_id: self.trade_id(amount),
                 order_id: self.order_id.clone(),
                 limit_type: self.limit_type(),
-                user: self.user.as_deref().unwrap().to_owned(),
-                size: amount as u64,
-                price: price as u64,
+                user: user.to_owned(),
+                size: U128(amount),
+                price: U128(price),
                 block_number: self.block_number as u64,
-                timestamp: DateTime::from_timestamp(self.block_timestamp, 0)
-                    .unwrap()
-                    .naive_utc(),
+                timestamp: self.timestamp()?,
                 market_id: self.market_id.clone(),
+                decimals: None,
             })
         } else {
-            None
+            Err(self.reject("INVALID_TRADE: missing trade fields".to_string()))
+        }
+    }
+
+    /// Builds the journal entry of the event, which keeps the whole event as its payload.
+    pub fn build_raw_event(&self) -> RawEvent {
+        RawEvent {
+            market_id: self.market_id.clone(),
+            block_number: self.block_number as u64,
+            block_hash: self.block_hash.clone(),
+            transaction_hash: self.transaction_hash.clone(),
+            transaction_index: self.transaction_index,
+            log_index: self.log_index,
+            payload: serde_json::to_string(self).unwrap_or_default(),
+        }
+    }
+
+    /// Builds the cancellation of the event.
+    ///
+    /// # Errors
+    ///
+    /// Rejects the event if its timestamp is invalid.
+    pub fn build_cancel(&self) -> Result<Cancellation, Rejection> {
+        Ok(Cancellation {
+            order_id: self.order_id.clone(),
+            tx_id: self.transaction_hash.clone(),
+            block_number: self.block_number,
+            timestamp: self.timestamp()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: &str) -> PangeaEvent {
+        PangeaEvent {
+            chain: 9889,
+            block_number: 10,
+            block_hash: "0xblock".to_string(),
+            block_timestamp: 1_700_000_000,
+            transaction_hash: "0xtx".to_string(),
+            transaction_index: 3,
+            log_index: 4,
+            market_id: "0xmarket".to_string(),
+            order_id: "0xorder".to_string(),
+            event_type: Some(event_type.to_string()),
+            asset: Some("0xasset".to_string()),
+            amount: Some(500),
+            asset_type: None,
+            order_type: Some("Sell".to_string()),
+            price: Some(70_000),
+            user: Some("0xuser".to_string()),
+            order_matcher: Some("0xmatcher".to_string()),
+            owner: None,
+            limit_type: Some("GTC".to_string()),
+        }
+    }
+
+    #[test]
+    fn builds_the_updates_of_valid_events() {
+        let order = event("Open").build_order().unwrap();
+        assert_eq!(order.initial_amount, U128::from(500u128));
+        assert_eq!(order.timestamp.and_utc().timestamp(), 1_700_000_000);
+
+        let trade = event("Trade").build_trade().unwrap();
+        assert_eq!(trade.size, U128::from(500u128));
+        assert_eq!(trade.trade_id, event("Trade").trade_id(500));
+
+        let cancel = event("Cancel").build_cancel().unwrap();
+        assert_eq!(cancel.order_id, "0xorder");
+    }
+
+    #[test]
+    fn rejects_the_events_with_missing_fields() {
+        let mut open = event("Open");
+        open.price = None;
+        let rejection = open.build_order().unwrap_err();
+        assert_eq!(rejection.block_number, Some(10));
+        assert_eq!(rejection.reason, "INVALID_ORDER: missing order fields");
+
+        let mut trade = event("Trade");
+        trade.amount = None;
+        let rejection = trade.build_trade().unwrap_err();
+        assert_eq!(rejection.reason, "INVALID_TRADE: missing trade fields");
+        assert!(rejection.payload.contains("\"amount\":null"));
+    }
+
+    #[test]
+    fn rejects_the_events_with_an_out_of_range_timestamp() {
+        for event_type in ["Open", "Trade", "Cancel"] {
+            let mut event = event(event_type);
+            event.block_timestamp = i64::MAX;
+
+            let rejection = match event_type {
+                "Open" => event.build_order().map(drop),
+                "Trade" => event.build_trade().map(drop),
+                _ => event.build_cancel().map(drop),
+            }
+            .unwrap_err();
+
+            assert_eq!(rejection.block_number, Some(10));
+            assert_eq!(
+                rejection.reason,
+                format!("INVALID_TIMESTAMP: {} is out of range", i64::MAX)
+            );
         }
     }
 }
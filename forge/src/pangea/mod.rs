@@ -1,4 +1,5 @@
+pub mod cache;
 pub mod event;
-pub mod indexer;
+pub mod source;
 
-pub use indexer::*;
+pub use source::*;
@@ -0,0 +1,327 @@
+use ethers_core::types::H256;
+use fuels::accounts::provider::Provider;
+use futures::{future::BoxFuture, stream, FutureExt, StreamExt, TryStreamExt};
+use pangea_client::{
+    provider::FuelProvider, query::Bound, requests::fuel::GetSparkOrderRequest, ChainId, Client,
+    ClientBuilder, Format, WsProvider,
+};
+use std::{collections::HashSet, str::FromStr, sync::Arc};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::{
+    config::Config,
+    error::Error,
+    pangea::{cache::BatchCache, event::PangeaEvent},
+    source::{EventSource, EventStream, SourceEvent},
+    types::Rejection,
+};
+
+/// Number of decoded events buffered ahead of the indexer.
+const EVENT_BUFFER_SIZE: usize = 1024;
+/// Number of blocks of a cached batch, the batches are aligned to multiples of it so they
+/// are reused whatever block the catch-up starts from.
+const CACHE_BATCH_SIZE: i64 = 10_000;
+/// Number of blocks below the chain head a batch has to end at to be cached, so the batches
+/// which may still be reorganized are never cached.
+const CACHE_CONFIRMATIONS: i64 = 64;
+/// Number of batches of a range read at once through the cache, the one being streamed and
+/// the next one.
+const CACHE_BATCHES_IN_FLIGHT: usize = 2;
+
+pub struct PangeaSource {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    pangea_host: String,
+    username: String,
+    password: String,
+    provider: Provider,
+    chain_id: ChainId,
+    market_id: String,
+    market_h256: H256,
+    cache: Option<BatchCache>,
+}
+
+impl PangeaSource {
+    pub async fn create(config: &Config, market_id: &str) -> Result<Self, Error> {
+        let network = config.network();
+        let provider = Provider::connect(config.fuel_url()).await?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                pangea_host: config.pangea_host.clone(),
+                username: config.pangea_username.clone().unwrap_or_default(),
+                password: config.pangea_password.clone().unwrap_or_default(),
+                provider,
+                chain_id: network.chain_id(),
+                market_id: market_id.to_string(),
+                market_h256: H256::from_str(market_id)?,
+                cache: config.cache_dir.as_ref().map(BatchCache::new),
+            }),
+        })
+    }
+}
+
+impl Inner {
+    pub async fn create_pangea_client(&self) -> Result<Client<WsProvider>, Error> {
+        let pangea_client = ClientBuilder::default()
+            .endpoint(&self.pangea_host)
+            .credential(&self.username, &self.password)
+            .build::<WsProvider>()
+            .await?;
+
+        Ok(pangea_client)
+    }
+
+    fn request(&self, from_block: Bound, to_block: Bound) -> GetSparkOrderRequest {
+        GetSparkOrderRequest {
+            from_block,
+            to_block,
+            market_id__in: HashSet::from([self.market_h256]),
+            chains: HashSet::from([self.chain_id]),
+            ..Default::default()
+        }
+    }
+
+    /// Requests spark orders from Pangea and forwards them as decoded events.
+    ///
+    /// The Pangea stream is driven by a separate task which owns the client, so the returned
+    /// stream isn't tied to the lifetime of the source. The task stops as soon as the stream
+    /// is dropped, or once it forwards an error of the Pangea stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_block` - The first block of the request.
+    /// * `to_block` - The last block of the request, or `Bound::Subscribe` to follow new blocks.
+    /// * `deltas` - Whether to request the deltas (live) stream.
+    ///
+    async fn stream_events(
+        &self,
+        from_block: Bound,
+        to_block: Bound,
+        deltas: bool,
+    ) -> Result<EventStream, Error> {
+        let client = self.create_pangea_client().await?;
+        let request = self.request(from_block, to_block);
+
+        let (ready_tx, ready_rx) = oneshot::channel::<Result<(), Error>>();
+        let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER_SIZE);
+
+        tokio::spawn(async move {
+            let stream = match client
+                .get_fuel_spark_orders_by_format(request, Format::JsonStream, deltas)
+                .await
+            {
+                Ok(stream) => {
+                    let _ = ready_tx.send(Ok(()));
+                    stream
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e.into()));
+                    return;
+                }
+            };
+            futures::pin_mut!(stream);
+
+            while let Some(data) = stream.next().await {
+                match data {
+                    Ok(data) => {
+                        if event_tx
+                            .send(Ok(PangeaEvent::from_bytes(data)))
+                            .await
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Error in the stream of events: {e}");
+                        let _ = event_tx.send(Err(e.into())).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        ready_rx.await.unwrap_or(Err(Error::StreamClosed))?;
+
+        Ok(ReceiverStream::new(event_rx).boxed())
+    }
+
+    /// Requests the raw spark orders of a block range from Pangea.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the stream of events breaks, so an incomplete range is never returned.
+    async fn fetch_raw(&self, from_block: i64, to_block: i64) -> Result<Vec<Vec<u8>>, Error> {
+        let client = self.create_pangea_client().await?;
+        let request = self.request(Bound::Exact(from_block), Bound::Exact(to_block));
+
+        let stream = client
+            .get_fuel_spark_orders_by_format(request, Format::JsonStream, false)
+            .await?;
+        futures::pin_mut!(stream);
+
+        let mut events = Vec::new();
+        while let Some(data) = stream.next().await {
+            events.push(data?);
+        }
+
+        Ok(events)
+    }
+
+    /// Reads the events of a block range through the cache.
+    ///
+    /// The range is covered by batches aligned to `CACHE_BATCH_SIZE` blocks. They are read by
+    /// separate tasks, the first one as soon as the stream is created so the ranges requested
+    /// ahead are read concurrently, then each one while the previous one is streamed. At most
+    /// `CACHE_BATCHES_IN_FLIGHT` batches are held in memory. A batch which can't be fetched is
+    /// yielded as an error and ends the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `from_block` - The first block of the range.
+    /// * `to_block` - The last block of the range.
+    /// * `cached_to` - The last block a cached batch may end at.
+    ///
+    fn fetch_cached(
+        self: Arc<Self>,
+        from_block: i64,
+        to_block: i64,
+        cached_to: i64,
+    ) -> EventStream {
+        let first_batch = from_block - from_block.rem_euclid(CACHE_BATCH_SIZE);
+
+        let mut reads = (first_batch..=to_block)
+            .step_by(CACHE_BATCH_SIZE as usize)
+            .map(move |batch_from| {
+                let inner = Arc::clone(&self);
+                tokio::spawn(async move {
+                    inner
+                        .read_batch(batch_from, from_block, to_block, cached_to)
+                        .await
+                })
+            });
+        // The reads are started as they are taken from the iterator
+        let first_read = reads.next();
+
+        stream::iter(first_read.into_iter().chain(reads))
+            .map(|read| async move { read.await? })
+            .buffered(CACHE_BATCHES_IN_FLIGHT)
+            .scan(false, |failed, events| {
+                // Nothing is yielded past the first error
+                let item = (!*failed).then_some(events);
+                *failed = item.as_ref().is_some_and(Result::is_err);
+                futures::future::ready(item)
+            })
+            .map_ok(|events| stream::iter(events.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Reads the events of a cached batch within the `[from_block, to_block]` range.
+    ///
+    /// A batch is read from the cache, or fetched as a whole from Pangea and cached. A batch
+    /// which goes past `cached_to` may not be complete or final yet, so only its part within
+    /// the range is fetched and it isn't cached.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the batch can't be fetched, so a batch is never partially read.
+    async fn read_batch(
+        &self,
+        batch_from: i64,
+        from_block: i64,
+        to_block: i64,
+        cached_to: i64,
+    ) -> Result<Vec<SourceEvent>, Error> {
+        let batch_to = batch_from + CACHE_BATCH_SIZE - 1;
+
+        let raw_events = match &self.cache {
+            Some(cache) if batch_to <= cached_to => {
+                match cache.read(&self.market_id, batch_from, batch_to).await {
+                    Some(raw_events) => raw_events,
+                    None => {
+                        let raw_events = self.fetch_raw(batch_from, batch_to).await?;
+                        if let Err(e) = cache
+                            .write(&self.market_id, batch_from, batch_to, raw_events.clone())
+                            .await
+                        {
+                            log::warn!("CACHE_WRITE_FAILED: {}", e);
+                        }
+                        raw_events
+                    }
+                }
+            }
+            _ => {
+                self.fetch_raw(batch_from.max(from_block), batch_to.min(to_block))
+                    .await?
+            }
+        };
+
+        // Events of an unknown block are read along with the first block of the batch
+        let owns_batch_start = batch_from >= from_block;
+        let events = raw_events
+            .into_iter()
+            .map(PangeaEvent::from_bytes)
+            .filter(|event| {
+                let block_number = match event {
+                    Ok(event) => Some(event.block_number),
+                    Err(Rejection { block_number, .. }) => *block_number,
+                };
+
+                block_number.map_or(owns_batch_start, |block_number| {
+                    block_number >= from_block && block_number <= to_block
+                })
+            })
+            .collect();
+
+        Ok(events)
+    }
+}
+
+impl EventSource for PangeaSource {
+    fn latest_block(&self) -> BoxFuture<'_, Result<i64, Error>> {
+        async move { Ok(self.inner.provider.latest_block_height().await? as i64) }.boxed()
+    }
+
+    fn block_hash(&self, block_number: i64) -> BoxFuture<'_, Result<Option<String>, Error>> {
+        async move {
+            let block = self
+                .inner
+                .provider
+                .block_by_height((block_number as u32).into())
+                .await?;
+
+            Ok(block.map(|block| format!("{:#x}", block.id)))
+        }
+        .boxed()
+    }
+
+    fn fetch(&self, from_block: i64, to_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move {
+            match &self.inner.cache {
+                Some(_) => {
+                    let latest_block = self.latest_block().await?;
+                    let cached_to = to_block.min(latest_block - CACHE_CONFIRMATIONS);
+
+                    Ok(Arc::clone(&self.inner).fetch_cached(from_block, to_block, cached_to))
+                }
+                None => {
+                    self.inner
+                        .stream_events(Bound::Exact(from_block), Bound::Exact(to_block), false)
+                        .await
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn subscribe(&self, from_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        self.inner
+            .stream_events(Bound::Exact(from_block), Bound::Subscribe, true)
+            .boxed()
+    }
+}
@@ -0,0 +1,66 @@
+use sea_orm::DatabaseConnection;
+use std::sync::Arc;
+
+use crate::{
+    config::{Config, MarketInfo},
+    dispatcher::{operation_channel, OperationDispatcher},
+    error::Error,
+    indexer::Indexer,
+    journal::JournalSource,
+    source::EventSource,
+};
+
+/// Rebuilds the orders and trades of a market from its journal, e.g. once a bug of the
+/// dispatcher is fixed.
+///
+/// Everything derived from the events of the market is wiped first, then the journaled events
+/// are applied again in their on-chain order, exactly as if they were indexed from Pangea. The
+/// journal itself is left untouched. The indexer of the market mustn't run meanwhile.
+///
+/// # Arguments
+///
+/// * `db_conn` - The database connection.
+/// * `config` - The forge config.
+/// * `market` - The market to rebuild.
+///
+pub async fn rebuild(
+    db_conn: Arc<DatabaseConnection>,
+    config: &Config,
+    market: &MarketInfo,
+) -> Result<(), Error> {
+    let source = JournalSource::new(Arc::clone(&db_conn), &market.id);
+    let latest_block = source.latest_block().await?;
+
+    let (operation_tx, operation_rx) = operation_channel();
+    let operation_dispatcher = OperationDispatcher::new(
+        market.id.clone(),
+        market.name.clone(),
+        db_conn,
+        operation_rx,
+        false,
+    );
+    // The dispatcher stops once the indexer is done and its operations are processed
+    let dispatcher_handle = tokio::spawn(async move {
+        operation_dispatcher.start().await;
+    });
+
+    log::info!("[{}] REBUILD: up to block {}", market.name, latest_block);
+    let indexer = Indexer::new(
+        Box::new(source),
+        &market.name,
+        operation_tx,
+        config.batch_size(market),
+        config.catch_up_concurrency,
+        config.reconnect,
+        None,
+    );
+    indexer.prune(0).await?;
+    indexer.catch_up(0, latest_block).await?;
+
+    drop(indexer);
+    dispatcher_handle.await?;
+
+    log::info!("[{}] REBUILT", market.name);
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use sea_orm::DatabaseConnection;
+use sparker_core::repo;
+use std::sync::Arc;
+
+use crate::{
+    config::MarketInfo,
+    dispatcher::{operation_channel, Operation, OperationDispatcher},
+    error::Error,
+    indexer::build_update,
+    pangea::event::PangeaEvent,
+};
+
+/// Applies the pending dead letters of a market again, e.g. once the bug which rejected them
+/// is fixed.
+///
+/// The events are applied in their on-chain order without moving the latest processed block.
+/// Every pending dead letter is marked as redriven, an event which is rejected again ends up
+/// in a new dead letter.
+///
+/// # Arguments
+///
+/// * `db_conn` - The database connection.
+/// * `market` - The market to redrive the dead letters of.
+///
+pub async fn redrive(db_conn: Arc<DatabaseConnection>, market: &MarketInfo) -> Result<(), Error> {
+    let dead_letters = repo::dead_letter::Query::find_pending(db_conn.as_ref(), &market.id).await?;
+    if dead_letters.is_empty() {
+        log::info!("[{}] NO_DEAD_LETTERS", market.name);
+        return Ok(());
+    }
+
+    let (operation_tx, operation_rx) = operation_channel();
+    let operation_dispatcher = OperationDispatcher::new(
+        market.id.clone(),
+        market.name.clone(),
+        db_conn,
+        operation_rx,
+        false,
+    );
+    // The dispatcher stops once the queued operations are processed
+    let dispatcher_handle = tokio::spawn(async move {
+        operation_dispatcher.start().await;
+    });
+
+    let ids = dead_letters
+        .iter()
+        .map(|dead_letter| dead_letter.id)
+        .collect::<Vec<i32>>();
+    log::info!("[{}] REDRIVE: {} dead letters", market.name, ids.len());
+
+    for dead_letter in dead_letters {
+        let update = PangeaEvent::from_json(&dead_letter.payload)
+            .and_then(|event| build_update(Arc::new(event)));
+
+        let operation = match update {
+            Ok(Some(update)) => Operation::Update(update),
+            Ok(None) => continue,
+            Err(rejection) => Operation::DeadLetter(rejection),
+        };
+        operation_tx
+            .send(operation)
+            .await
+            .map_err(|_| Error::DispatcherStopped(market.name.clone()))?;
+    }
+    operation_tx
+        .send(Operation::Redrive(ids))
+        .await
+        .map_err(|_| Error::DispatcherStopped(market.name.clone()))?;
+
+    drop(operation_tx);
+    dispatcher_handle.await?;
+
+    Ok(())
+}
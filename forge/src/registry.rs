@@ -0,0 +1,206 @@
+use chrono::Utc;
+use sea_orm::DatabaseConnection;
+use sparker_core::{repo, Market, MarketAsset};
+
+use crate::{
+    config::{AssetInfo, Config, MarketInfo},
+    error::Error,
+    fuel::market::{read_config, ContractConfig},
+};
+
+/// Stores the metadata of a market, so the clients don't need to know the decimals of its
+/// assets.
+///
+/// The metadata comes from the config, the market contract is read only for what the config
+/// leaves unset.
+///
+/// # Errors
+///
+/// Fails if a field is known to neither the config nor the contract rather than storing a
+/// made-up value, e.g. because the contract can't be read.
+pub async fn register(
+    db_conn: &DatabaseConnection,
+    config: &Config,
+    market: &MarketInfo,
+) -> Result<(), Error> {
+    let contract = if market.base_asset.is_partial()
+        || market.quote_asset.is_partial()
+        || market.price_precision.is_none()
+    {
+        // What the config sets is still used, the fields left unset are reported below
+        read_config(config, &market.id)
+            .await
+            .inspect_err(|e| log::warn!("[{}] MARKET_CONTRACT_UNREADABLE: {}", market.name, e))
+            .ok()
+    } else {
+        None
+    };
+
+    let metadata = merge_market(market, config.network().name(), contract.as_ref())?;
+    repo::market::Mutation::upsert(db_conn, metadata).await?;
+
+    Ok(())
+}
+
+/// Builds the metadata of a market from the config, with what the contract has for the fields
+/// the config leaves unset.
+///
+/// # Arguments
+///
+/// * `market` - The market of the config.
+/// * `chain` - The name of the network of the market.
+/// * `contract` - The config of the market contract, if it was read.
+///
+/// # Errors
+///
+/// Fails if a field is set by neither the config nor the contract.
+fn merge_market(
+    market: &MarketInfo,
+    chain: &str,
+    contract: Option<&ContractConfig>,
+) -> Result<Market, Error> {
+    Ok(Market {
+        market_id: market.id.clone(),
+        name: market.name.clone(),
+        chain: chain.to_string(),
+        base_asset: merge_asset(
+            market,
+            "base",
+            &market.base_asset,
+            contract.map(|contract| (&contract.base_asset, contract.base_decimals)),
+        )?,
+        quote_asset: merge_asset(
+            market,
+            "quote",
+            &market.quote_asset,
+            contract.map(|contract| (&contract.quote_asset, contract.quote_decimals)),
+        )?,
+        price_precision: market
+            .price_precision
+            .or(contract.map(|contract| contract.price_decimals))
+            .ok_or_else(|| missing_field(market, "price precision"))?,
+        updated_at: Utc::now().naive_utc(),
+    })
+}
+
+/// Fills in what the config leaves unset of an asset with what the contract has.
+///
+/// # Arguments
+///
+/// * `market` - The market of the asset, used in the errors.
+/// * `side` - Whether the asset is the base or the quote one, used in the errors.
+/// * `asset` - The asset of the config.
+/// * `contract` - The id and decimals of the asset read from the contract, if it was read.
+///
+fn merge_asset(
+    market: &MarketInfo,
+    side: &str,
+    asset: &AssetInfo,
+    contract: Option<(&String, u32)>,
+) -> Result<MarketAsset, Error> {
+    Ok(MarketAsset {
+        id: asset
+            .id
+            .clone()
+            .or(contract.map(|(id, _)| id.clone()))
+            .ok_or_else(|| missing_field(market, &format!("{} asset id", side)))?,
+        symbol: asset.symbol.clone(),
+        decimals: asset
+            .decimals
+            .or(contract.map(|(_, decimals)| decimals))
+            .ok_or_else(|| missing_field(market, &format!("{} asset decimals", side)))?,
+    })
+}
+
+fn missing_field(market: &MarketInfo, field: &str) -> Error {
+    Error::Config(format!(
+        "The {} of market {} is set by neither the config nor the contract",
+        field, market.name
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(id: Option<&str>, decimals: Option<u32>) -> AssetInfo {
+        AssetInfo {
+            id: id.map(str::to_string),
+            symbol: None,
+            decimals,
+        }
+    }
+
+    fn market(base_asset: AssetInfo, price_precision: Option<u32>) -> MarketInfo {
+        MarketInfo {
+            id: "0xmarket".to_string(),
+            name: "BTC-USDC".to_string(),
+            start_block: None,
+            batch_size: None,
+            base_asset,
+            quote_asset: asset(Some("0xusdc"), Some(6)),
+            price_precision,
+        }
+    }
+
+    fn contract() -> ContractConfig {
+        ContractConfig {
+            base_asset: "0xbtc".to_string(),
+            base_decimals: 8,
+            quote_asset: "0xquote".to_string(),
+            quote_decimals: 9,
+            price_decimals: 9,
+        }
+    }
+
+    #[test]
+    fn fills_in_the_fields_unset_by_the_config_from_the_contract() {
+        let market = market(asset(None, Some(10)), None);
+        let metadata = merge_market(&market, "mainnet", Some(&contract())).unwrap();
+
+        assert_eq!(metadata.chain, "mainnet");
+        assert_eq!(metadata.base_asset.id, "0xbtc");
+        assert_eq!(metadata.base_asset.decimals, 10);
+        assert_eq!(metadata.quote_asset.id, "0xusdc");
+        assert_eq!(metadata.quote_asset.decimals, 6);
+        assert_eq!(metadata.price_precision, 9);
+    }
+
+    #[test]
+    fn rejects_a_field_unset_by_the_config_without_the_contract() {
+        let cases = [
+            (market(asset(None, Some(8)), Some(9)), "base asset id"),
+            (
+                market(asset(Some("0xbtc"), None), Some(9)),
+                "base asset decimals",
+            ),
+            (
+                market(asset(Some("0xbtc"), Some(8)), None),
+                "price precision",
+            ),
+        ];
+
+        for (market, field) in cases {
+            let Err(Error::Config(message)) = merge_market(&market, "mainnet", None) else {
+                panic!("the {} is unset", field);
+            };
+            assert_eq!(
+                message,
+                format!(
+                    "The {} of market BTC-USDC is set by neither the config nor the contract",
+                    field
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn uses_the_config_alone_when_it_sets_every_field() {
+        let market = market(asset(Some("0xbtc"), Some(8)), Some(9));
+        let metadata = merge_market(&market, "testnet", None).unwrap();
+
+        assert_eq!(metadata.base_asset.id, "0xbtc");
+        assert_eq!(metadata.base_asset.decimals, 8);
+        assert_eq!(metadata.price_precision, 9);
+    }
+}
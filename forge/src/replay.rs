@@ -0,0 +1,137 @@
+use futures::{
+    future::{self, BoxFuture},
+    stream, FutureExt, Stream, StreamExt, TryStreamExt,
+};
+use std::path::{Path, PathBuf};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, BufReader},
+};
+use tokio_stream::wrappers::LinesStream;
+
+use crate::{
+    error::Error,
+    pangea::event::PangeaEvent,
+    source::{EventSource, EventStream, SourceEvent},
+};
+
+/// Event source which replays a recorded JSONL dump of Pangea events.
+///
+/// Every line of the file is a single event in the Pangea `JsonStream` format, in chain order.
+/// Events of other markets are skipped, so one dump can be shared by all the markets.
+pub struct ReplaySource {
+    path: PathBuf,
+    market_id: String,
+}
+
+impl ReplaySource {
+    pub fn new(path: impl AsRef<Path>, market_id: &str) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            market_id: market_id.to_string(),
+        }
+    }
+
+    /// Reads the market events of the dump which satisfy the block filter.
+    ///
+    /// Lines which can't be decoded are rejected unless they belong to another market. A read
+    /// error is yielded and ends the stream.
+    async fn read_events(
+        &self,
+        block_filter: impl Fn(i64) -> bool + Send + 'static,
+    ) -> Result<impl Stream<Item = Result<SourceEvent, Error>> + Send + 'static, Error> {
+        let file = File::open(&self.path).await?;
+        let lines = LinesStream::new(BufReader::new(file).lines());
+        let market_id = self.market_id.clone();
+
+        let events = lines
+            .scan(false, |failed, line| {
+                // Nothing is read past the first error
+                if *failed {
+                    return future::ready(None);
+                }
+                if let Err(e) = &line {
+                    log::error!("Error while reading the replay file: {e}");
+                    *failed = true;
+                }
+                future::ready(Some(line))
+            })
+            .filter_map(move |line| {
+                let event = match line {
+                    Ok(line) if line.trim().is_empty() => None,
+                    Ok(line) => match PangeaEvent::from_json(&line) {
+                        Ok(event)
+                            if event.market_id.eq_ignore_ascii_case(&market_id)
+                                && block_filter(event.block_number) =>
+                        {
+                            Some(Ok(Ok(event)))
+                        }
+                        Ok(_) => None,
+                        Err(rejection)
+                            if is_market_line(&line, &market_id)
+                                && rejection.block_number.is_none_or(&block_filter) =>
+                        {
+                            Some(Ok(Err(rejection)))
+                        }
+                        Err(_) => None,
+                    },
+                    Err(e) => Some(Err(e.into())),
+                };
+
+                future::ready(event)
+            });
+
+        Ok(events)
+    }
+}
+
+/// Whether an undecodable line may belong to the market, i.e. it has no other market id.
+fn is_market_line(line: &str, market_id: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(line)
+        .ok()
+        .and_then(|value| value.get("market_id")?.as_str().map(str::to_owned))
+        .is_none_or(|line_market_id| line_market_id.eq_ignore_ascii_case(market_id))
+}
+
+impl EventSource for ReplaySource {
+    fn latest_block(&self) -> BoxFuture<'_, Result<i64, Error>> {
+        async move {
+            let latest = self
+                .read_events(|_| true)
+                .await?
+                .try_fold(0, |latest, event| async move {
+                    Ok(event.map_or(latest, |event| latest.max(event.block_number)))
+                })
+                .await?;
+
+            Ok(latest)
+        }
+        .boxed()
+    }
+
+    fn block_hash(&self, _block_number: i64) -> BoxFuture<'_, Result<Option<String>, Error>> {
+        // The dump is the whole chain, it's never reorganized
+        async move { Ok(None) }.boxed()
+    }
+
+    fn fetch(&self, from_block: i64, to_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move {
+            let events = self
+                .read_events(move |block| block >= from_block && block <= to_block)
+                .await?;
+
+            Ok(events.boxed())
+        }
+        .boxed()
+    }
+
+    fn subscribe(&self, from_block: i64) -> BoxFuture<'_, Result<EventStream, Error>> {
+        async move {
+            let events = self.read_events(move |block| block >= from_block).await?;
+
+            // The dump is the whole chain, so once it's replayed the tail just stays quiet
+            Ok(events.chain(stream::pending()).boxed())
+        }
+        .boxed()
+    }
+}
@@ -0,0 +1,28 @@
+use futures::{future::BoxFuture, stream::BoxStream};
+
+use crate::{error::Error, pangea::event::PangeaEvent, types::Rejection};
+
+/// An event yielded by an [`EventSource`], or the rejection of an event which can't be decoded.
+pub type SourceEvent = Result<PangeaEvent, Rejection>;
+
+/// Stream of events produced by an [`EventSource`].
+pub type EventStream = BoxStream<'static, Result<SourceEvent, Error>>;
+
+/// Source of market events consumed by the [`Indexer`](crate::indexer::Indexer).
+///
+/// Events must be yielded in chain order. A transport error is yielded as an `Err` item and
+/// ends the stream, so the events which follow it are never silently skipped. Events that
+/// can't be decoded are yielded as rejections, they are dead-lettered and the indexing goes on.
+pub trait EventSource: Send + Sync {
+    /// Returns the latest block known to the source.
+    fn latest_block(&self) -> BoxFuture<'_, Result<i64, Error>>;
+
+    /// Returns the hash of a block of the canonical chain, `None` if the source can't tell.
+    fn block_hash(&self, block_number: i64) -> BoxFuture<'_, Result<Option<String>, Error>>;
+
+    /// Returns the events in the `[from_block, to_block]` range.
+    fn fetch(&self, from_block: i64, to_block: i64) -> BoxFuture<'_, Result<EventStream, Error>>;
+
+    /// Returns the events starting from `from_block` and following the chain head.
+    fn subscribe(&self, from_block: i64) -> BoxFuture<'_, Result<EventStream, Error>>;
+}
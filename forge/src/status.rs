@@ -0,0 +1,96 @@
+use chrono::DateTime;
+use sea_orm::DatabaseConnection;
+use sparker_core::{repo, IndexerMode, IndexerStatus};
+use std::sync::{Arc, Mutex};
+use tokio::time::{sleep, Duration};
+
+use crate::types::{stopped, StopSignal};
+
+/// Interval between the writes of the status to the database.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Keeps track of the status of the indexer of a market and stores it along with the state of
+/// the market, so the API can tell how fresh the market data is.
+///
+/// The status is kept in memory and written periodically, reporting is cheap enough to be done
+/// on every event.
+pub struct StatusReporter {
+    market_id: String,
+    db_conn: Arc<DatabaseConnection>,
+    status: Mutex<IndexerStatus>,
+}
+
+impl StatusReporter {
+    pub fn new(market_id: &str, db_conn: Arc<DatabaseConnection>) -> Self {
+        Self {
+            market_id: market_id.to_string(),
+            db_conn,
+            status: Mutex::new(IndexerStatus::default()),
+        }
+    }
+
+    pub fn set_mode(&self, mode: IndexerMode) {
+        self.update(|status| status.mode = mode);
+    }
+
+    pub fn set_chain_head(&self, block: i64) {
+        self.update(|status| status.chain_head = Some(block as u64));
+    }
+
+    /// Records the block timestamp of the latest event.
+    pub fn set_last_event(&self, block_timestamp: i64) {
+        let last_event_at = DateTime::from_timestamp(block_timestamp, 0).map(|t| t.naive_utc());
+        self.update(|status| status.last_event_at = last_event_at);
+    }
+
+    pub fn set_error(&self, error: String) {
+        self.update(|status| status.last_error = Some(error));
+    }
+
+    /// Clears the latest error once the indexer recovered from it.
+    pub fn clear_error(&self) {
+        self.update(|status| status.last_error = None);
+    }
+
+    pub fn add_reconnect(&self) {
+        self.update(|status| status.reconnect_count += 1);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut IndexerStatus)) {
+        f(&mut self.status.lock().unwrap());
+    }
+
+    /// Stores the status whenever it changes, and a last time once stopped.
+    ///
+    /// A status which can't be stored yet, or fails to be stored, is kept until the next
+    /// attempt.
+    pub async fn start(&self, mut stop: StopSignal) {
+        let mut stored = None;
+
+        loop {
+            let stopping = tokio::select! {
+                _ = sleep(FLUSH_INTERVAL) => false,
+                _ = stopped(&mut stop) => true,
+            };
+
+            let status = self.status.lock().unwrap().clone();
+            if stored.as_ref() != Some(&status) {
+                match repo::state::Mutation::update_status(
+                    self.db_conn.as_ref(),
+                    &self.market_id,
+                    &status,
+                )
+                .await
+                {
+                    Ok(true) => stored = Some(status),
+                    Ok(false) => {}
+                    Err(e) => log::error!("[{}] STATUS_UPDATE_ERROR: {}", self.market_id, e),
+                }
+            }
+
+            if stopping {
+                return;
+            }
+        }
+    }
+}
@@ -1,8 +1,53 @@
+use chrono::NaiveDateTime;
 use std::sync::Arc;
-use tokio::sync::{
-    mpsc::{UnboundedReceiver, UnboundedSender},
-    Mutex,
-};
+use tokio::sync::{mpsc, watch, Mutex};
 
-pub type Sender<T> = Arc<UnboundedSender<T>>;
-pub type Receiver<T> = Arc<Mutex<UnboundedReceiver<T>>>;
+pub type Sender<T> = Arc<mpsc::Sender<T>>;
+pub type Receiver<T> = Arc<Mutex<mpsc::Receiver<T>>>;
+/// Tells the tasks of a market to stop, it's set once on shutdown.
+pub type StopSignal = watch::Receiver<bool>;
+
+/// Waits until the stop signal is set.
+pub async fn stopped(stop: &mut StopSignal) {
+    // The signal can't be set anymore once its sender is dropped
+    if stop.wait_for(|stop| *stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Position of an event in the chain.
+///
+/// Positions are ordered the same way the events were emitted on-chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub block_number: i64,
+    pub transaction_index: u64,
+    pub log_index: u64,
+}
+
+impl Position {
+    /// Position after every event.
+    pub const MAX: Position = Position {
+        block_number: i64::MAX,
+        transaction_index: u64::MAX,
+        log_index: u64::MAX,
+    };
+}
+
+/// An event which couldn't be applied, stored as a dead letter.
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    /// Block of the event, unknown if the event couldn't be decoded.
+    pub block_number: Option<i64>,
+    pub payload: String,
+    pub reason: String,
+}
+
+/// Cancellation of an order.
+#[derive(Debug, Clone)]
+pub struct Cancellation {
+    pub order_id: String,
+    pub tx_id: String,
+    pub block_number: i64,
+    pub timestamp: NaiveDateTime,
+}
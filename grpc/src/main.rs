@@ -1,14 +1,15 @@
 use dotenv::dotenv;
 use sea_orm::DatabaseConnection;
 use sparker_core::{
-    repo::{order, trade},
-    Order,
+    repo::{market, order, state, trade},
+    AmountFormat, Market, Order,
 };
 use sparker_proto::{
     api::{
         orderbook_server::{Orderbook, OrderbookServer},
-        OrderRequest, OrderResponse, OrdersRequest, OrdersResponse, SpreadRequest, SpreadResponse,
-        TradeRequest, TradeResponse, TradesRequest, TradesResponse,
+        MarketStatusRequest, MarketStatusResponse, MarketsRequest, MarketsResponse, OrderRequest,
+        OrderResponse, OrdersRequest, OrdersResponse, SpreadRequest, SpreadResponse, TradeRequest,
+        TradeResponse, TradesRequest, TradesResponse,
     },
     types as proto, FILE_DESCRIPTOR_SET,
 };
@@ -27,6 +28,34 @@ pub struct RpcServer {
     events_tx: broadcast::Sender<Event>,
 }
 
+impl RpcServer {
+    /// Finds the metadata of a market to format its amounts and prices, if they are requested
+    /// as decimals.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `NOT_FOUND` if the decimal format is requested for a market without metadata.
+    async fn find_format_market(
+        &self,
+        market_id: &str,
+        format: i32,
+    ) -> Result<Option<Market>, Status> {
+        let format = proto::AmountFormat::from_repr(format)
+            .map(AmountFormat::from)
+            .unwrap_or_default();
+        if format == AmountFormat::Raw {
+            return Ok(None);
+        }
+
+        let market = market::Query::find_by_id(self.db_conn.as_ref(), market_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        market
+            .map(Some)
+            .ok_or_else(|| Status::not_found(format!("No metadata for market {}", market_id)))
+    }
+}
+
 #[tonic::async_trait]
 impl Orderbook for RpcServer {
     async fn list_orders(
@@ -38,6 +67,7 @@ impl Orderbook for RpcServer {
         let limit = request.limit;
         let order_type = proto::OrderType::from_repr(request.order_type);
         let user_ne = request.user_ne;
+        let market = self.find_format_market(&market_id, request.format).await?;
 
         let orders = match order_type {
             Some(order_type) => {
@@ -57,7 +87,10 @@ impl Orderbook for RpcServer {
 
         let orders = orders
             .into_iter()
-            .map(|order| order.into())
+            .map(|order| match &market {
+                Some(market) => order.with_decimals(market).into(),
+                None => order.into(),
+            })
             .collect::<Vec<proto::Order>>();
 
         let response = OrdersResponse { orders };
@@ -72,6 +105,7 @@ impl Orderbook for RpcServer {
         let request = request.into_inner();
         let market_id = request.market_id;
         let user = request.user;
+        let market = self.find_format_market(&market_id, request.format).await?;
         let mut events_rx = self.events_tx.subscribe();
 
         let (tx, rx) = mpsc::channel(4);
@@ -81,9 +115,13 @@ impl Orderbook for RpcServer {
                 match event {
                     // Only care about user order updates
                     Event::OrderUpdate(order)
-                        if user.as_ref().map_or(true, |user| &order.user == user)
+                        if user.as_ref().is_none_or(|user| &order.user == user)
                             && order.market_id == market_id =>
                     {
+                        let order = match &market {
+                            Some(market) => order.with_decimals(market),
+                            None => order,
+                        };
                         let response = OrderResponse {
                             order: Some(order.into()),
                         };
@@ -104,15 +142,23 @@ impl Orderbook for RpcServer {
         let request = request.into_inner();
         let market_id = request.market_id;
         let user_ne = request.user_ne;
+        let market = self.find_format_market(&market_id, request.format).await?;
+        let to_proto = |order: Order| -> proto::Order {
+            match &market {
+                Some(market) => order.with_decimals(market).into(),
+                None => order.into(),
+            }
+        };
+
         let best_bid =
             order::Query::find_best_bid(&self.db_conn, market_id.clone(), user_ne.clone())
                 .await
                 .unwrap()
-                .map(|o| o.into());
+                .map(to_proto);
         let best_ask = order::Query::find_best_ask(&self.db_conn, market_id, user_ne)
             .await
             .unwrap()
-            .map(|o| o.into());
+            .map(to_proto);
 
         let response = SpreadResponse { best_bid, best_ask };
         Ok(Response::new(response))
@@ -125,30 +171,72 @@ impl Orderbook for RpcServer {
         let request = request.into_inner();
         let market_id = request.market_id;
         let limit = request.limit;
+        let market = self.find_format_market(&market_id, request.format).await?;
 
         let trades = trade::Query::find(&self.db_conn, market_id, limit, 0)
             .await
             .unwrap();
         let trades = trades
             .into_iter()
-            .map(|trade| trade.into())
+            .map(|trade| match &market {
+                Some(market) => trade.with_decimals(market).into(),
+                None => trade.into(),
+            })
             .collect::<Vec<proto::Trade>>();
 
         let response = TradesResponse { trades };
         Ok(Response::new(response))
     }
 
+    async fn list_market_statuses(
+        &self,
+        request: Request<MarketStatusRequest>,
+    ) -> Result<Response<MarketStatusResponse>, Status> {
+        let request = request.into_inner();
+        let market_id = request.market_id;
+
+        let statuses = state::Query::find_statuses(&self.db_conn, market_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let statuses = statuses
+            .into_iter()
+            .map(|status| status.into())
+            .collect::<Vec<proto::MarketStatus>>();
+
+        let response = MarketStatusResponse { statuses };
+        Ok(Response::new(response))
+    }
+
+    async fn list_markets(
+        &self,
+        request: Request<MarketsRequest>,
+    ) -> Result<Response<MarketsResponse>, Status> {
+        let request = request.into_inner();
+        let market_id = request.market_id;
+
+        let markets = market::Query::find(&self.db_conn, market_id)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let markets = markets
+            .into_iter()
+            .map(|market| market.into())
+            .collect::<Vec<proto::Market>>();
+
+        let response = MarketsResponse { markets };
+        Ok(Response::new(response))
+    }
+
     type SubscribeTradesStream = ReceiverStream<Result<TradeResponse, Status>>;
     async fn subscribe_trades(
         &self,
         request: Request<TradeRequest>,
     ) -> Result<Response<Self::SubscribeTradesStream>, Status> {
         let request = request.into_inner();
-        let market_id = request.market_id;
-        let user = request.user;
+        let _market_id = request.market_id;
+        let _user = request.user;
         // let mut events_rx = self.events.subscribe();
 
-        let (tx, rx) = mpsc::channel(4);
+        let (_tx, rx) = mpsc::channel(4);
 
         // tokio::spawn(async move {
         //     while let Ok(event) = events_rx.recv().await {
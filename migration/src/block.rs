@@ -0,0 +1,11 @@
+use sea_orm_migration::prelude::*;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+pub enum Block {
+    Table,
+    Id,
+    MarketId,
+    BlockNumber,
+    BlockHash,
+}
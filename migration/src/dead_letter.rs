@@ -0,0 +1,13 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+pub enum DeadLetter {
+    Table,
+    Id,
+    MarketId,
+    BlockNumber,
+    Payload,
+    Reason,
+    CreatedAt,
+    RedrivenAt,
+}
@@ -1,11 +1,29 @@
 pub use sea_orm_migration::prelude::*;
 
+mod block;
+mod dead_letter;
 mod m20241101_130253_create_types;
 mod m20241101_130314_create_orders;
 mod m20241101_225432_create_trades;
 mod m20241104_075814_create_state;
 mod m20241203_152440_create_order_updates;
+mod m20261018_101500_create_blocks;
+mod m20261018_113000_create_order_undo;
+mod m20261018_120000_create_order_events;
+mod m20261018_130000_track_order_fills;
+mod m20261018_140000_add_order_event_outcomes;
+mod m20261018_150000_create_dead_letters;
+mod m20261018_160000_create_raw_events;
+mod m20261018_170000_track_indexer_status;
+mod m20261018_180000_store_amounts_as_numeric;
+mod m20261018_190000_create_markets;
+mod m20261018_200000_track_fill_volume;
+mod m20261018_210000_track_status_updates;
+mod market;
 mod order;
+mod order_event;
+mod order_undo;
+mod raw_event;
 mod state;
 mod trade;
 
@@ -20,6 +38,18 @@ impl MigratorTrait for Migrator {
             Box::new(m20241101_225432_create_trades::Migration),
             Box::new(m20241104_075814_create_state::Migration),
             Box::new(m20241203_152440_create_order_updates::Migration),
+            Box::new(m20261018_101500_create_blocks::Migration),
+            Box::new(m20261018_113000_create_order_undo::Migration),
+            Box::new(m20261018_120000_create_order_events::Migration),
+            Box::new(m20261018_130000_track_order_fills::Migration),
+            Box::new(m20261018_140000_add_order_event_outcomes::Migration),
+            Box::new(m20261018_150000_create_dead_letters::Migration),
+            Box::new(m20261018_160000_create_raw_events::Migration),
+            Box::new(m20261018_170000_track_indexer_status::Migration),
+            Box::new(m20261018_180000_store_amounts_as_numeric::Migration),
+            Box::new(m20261018_190000_create_markets::Migration),
+            Box::new(m20261018_200000_track_fill_volume::Migration),
+            Box::new(m20261018_210000_track_status_updates::Migration),
         ]
     }
 }
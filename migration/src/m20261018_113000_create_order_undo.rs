@@ -0,0 +1,42 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::order_undo::OrderUndo;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderUndo::Table)
+                    .if_not_exists()
+                    .col(pk_auto(OrderUndo::Id))
+                    .col(string(OrderUndo::MarketId))
+                    .col(string(OrderUndo::OrderId))
+                    .col(big_integer(OrderUndo::BlockNumber))
+                    .col(json_binary(OrderUndo::State))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-order_undo-market_id-block_number")
+                    .table(OrderUndo::Table)
+                    .col(OrderUndo::MarketId)
+                    .col(OrderUndo::BlockNumber)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderUndo::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,87 @@
+use sea_orm_migration::{
+    prelude::*, schema::*, sea_orm::Iterable, sea_query::extension::postgres::Type,
+};
+
+use crate::order::{Order, OrderStatusVariants};
+use crate::order_event::{OrderEvent, OrderEventType, OrderEventTypeVariants};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(OrderEventType)
+                    .values(OrderEventTypeVariants::iter())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(OrderEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(OrderEvent::Id))
+                    .col(string(OrderEvent::OrderId))
+                    .col(enumeration(
+                        OrderEvent::EventType,
+                        Alias::new("order_event_type"),
+                        OrderEventTypeVariants::iter(),
+                    ))
+                    .col(string(OrderEvent::TxId))
+                    .col(big_integer(OrderEvent::BlockNumber))
+                    .col(timestamp(OrderEvent::Timestamp))
+                    .col(big_integer(OrderEvent::AmountBefore))
+                    .col(big_integer(OrderEvent::AmountAfter))
+                    .col(enumeration(
+                        OrderEvent::Status,
+                        Alias::new("order_status"),
+                        OrderStatusVariants::iter(),
+                    ))
+                    .col(string(OrderEvent::MarketId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-order_event-order_id")
+                            .from(OrderEvent::Table, OrderEvent::OrderId)
+                            .to(Order::Table, Order::OrderId),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-order_event-order_id")
+                    .table(OrderEvent::Table)
+                    .col(OrderEvent::OrderId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-order_event-market_id-block_number")
+                    .table(OrderEvent::Table)
+                    .col(OrderEvent::MarketId)
+                    .col(OrderEvent::BlockNumber)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OrderEvent::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(OrderEventType).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,120 @@
+use sea_orm::Statement;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::order::Order;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .add_column(big_integer(Order::InitialAmount).default(0))
+                    .add_column(big_integer(Order::FilledAmount).default(0))
+                    .add_column(big_integer(Order::AverageFillPrice).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .rename_column(Order::Amount, Order::RemainingAmount)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Backfill the fills from the trades. Matched orders kept the amount they had before
+        // the last fill, so they are considered fully filled.
+        let backfill = [
+            r#"
+            UPDATE "order" o
+            SET filled_amount = t.filled_amount,
+                average_fill_price = t.average_fill_price
+            FROM (
+              SELECT order_id,
+                     SUM(size) AS filled_amount,
+                     (SUM(size::numeric * price) / SUM(size))::bigint AS average_fill_price
+              FROM trade
+              GROUP BY order_id
+              HAVING SUM(size) > 0
+            ) t
+            WHERE o.order_id = t.order_id;
+            "#,
+            r#"UPDATE "order" SET remaining_amount = 0 WHERE status = 'matched';"#,
+            r#"UPDATE "order" SET initial_amount = remaining_amount + filled_amount;"#,
+        ];
+        for sql in backfill {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned(),
+                ))
+                .await?;
+        }
+
+        // Bring the recorded order states in line with the new shape of the order
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                UPDATE order_undo u
+                SET state = (u.state - 'amount') || jsonb_build_object(
+                  'remaining_amount', u.state->'amount',
+                  'initial_amount', o.initial_amount,
+                  'filled_amount', GREATEST(o.initial_amount - (u.state->>'amount')::bigint, 0),
+                  'average_fill_price', o.average_fill_price
+                )
+                FROM "order" o
+                WHERE o.order_id = u.order_id AND u.state ? 'amount';
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                UPDATE order_undo
+                SET state = (state - 'remaining_amount' - 'initial_amount' - 'filled_amount'
+                  - 'average_fill_price') || jsonb_build_object('amount', state->'remaining_amount')
+                WHERE state ? 'remaining_amount';
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .rename_column(Order::RemainingAmount, Order::Amount)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .drop_column(Order::InitialAmount)
+                    .drop_column(Order::FilledAmount)
+                    .drop_column(Order::AverageFillPrice)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::dead_letter::DeadLetter;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DeadLetter::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DeadLetter::Id))
+                    .col(string(DeadLetter::MarketId))
+                    .col(big_integer_null(DeadLetter::BlockNumber))
+                    .col(text(DeadLetter::Payload))
+                    .col(text(DeadLetter::Reason))
+                    .col(timestamp(DeadLetter::CreatedAt))
+                    .col(timestamp_null(DeadLetter::RedrivenAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-dead_letter-market_id-block_number")
+                    .table(DeadLetter::Table)
+                    .col(DeadLetter::MarketId)
+                    .col(DeadLetter::BlockNumber)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DeadLetter::Table).to_owned())
+            .await
+    }
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::raw_event::RawEvent;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RawEvent::Table)
+                    .if_not_exists()
+                    .col(pk_auto(RawEvent::Id))
+                    .col(string(RawEvent::MarketId))
+                    .col(big_integer(RawEvent::BlockNumber))
+                    .col(string(RawEvent::BlockHash))
+                    .col(string(RawEvent::TransactionHash))
+                    .col(big_integer(RawEvent::TransactionIndex))
+                    .col(big_integer(RawEvent::LogIndex))
+                    .col(text(RawEvent::Payload))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-raw_event-transaction_hash-log_index")
+                    .table(RawEvent::Table)
+                    .col(RawEvent::TransactionHash)
+                    .col(RawEvent::LogIndex)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-raw_event-market_id-block_number")
+                    .table(RawEvent::Table)
+                    .col(RawEvent::MarketId)
+                    .col(RawEvent::BlockNumber)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(RawEvent::Table).to_owned())
+            .await
+    }
+}
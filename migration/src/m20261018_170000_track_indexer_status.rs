@@ -0,0 +1,61 @@
+use sea_orm_migration::{
+    prelude::*, schema::*, sea_orm::Iterable, sea_query::extension::postgres::Type,
+};
+
+use crate::state::{IndexerMode, IndexerModeVariants, State};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_type(
+                Type::create()
+                    .as_enum(IndexerMode)
+                    .values(IndexerModeVariants::iter())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(State::Table)
+                    .add_column(big_integer_null(State::ChainHead))
+                    .add_column(timestamp_null(State::LastEventAt))
+                    .add_column(
+                        enumeration(
+                            State::Mode,
+                            Alias::new("indexer_mode"),
+                            IndexerModeVariants::iter(),
+                        )
+                        .default("catching_up"),
+                    )
+                    .add_column(text_null(State::LastError))
+                    .add_column(integer(State::ReconnectCount).default(0))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(State::Table)
+                    .drop_column(State::ChainHead)
+                    .drop_column(State::LastEventAt)
+                    .drop_column(State::Mode)
+                    .drop_column(State::LastError)
+                    .drop_column(State::ReconnectCount)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_type(Type::drop().name(IndexerMode).to_owned())
+            .await
+    }
+}
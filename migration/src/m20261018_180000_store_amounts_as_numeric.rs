@@ -0,0 +1,126 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+use crate::{order::Order, order_event::OrderEvent, trade::Trade};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // NUMERIC(39, 0) holds any unsigned 128-bit integer
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .modify_column(ColumnDef::new(Order::InitialAmount).decimal_len(39, 0))
+                    .modify_column(ColumnDef::new(Order::FilledAmount).decimal_len(39, 0))
+                    .modify_column(ColumnDef::new(Order::RemainingAmount).decimal_len(39, 0))
+                    .modify_column(ColumnDef::new(Order::Price).decimal_len(39, 0))
+                    .modify_column(ColumnDef::new(Order::AverageFillPrice).decimal_len(39, 0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Trade::Table)
+                    .modify_column(ColumnDef::new(Trade::Size).decimal_len(39, 0))
+                    .modify_column(ColumnDef::new(Trade::Price).decimal_len(39, 0))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OrderEvent::Table)
+                    .modify_column(ColumnDef::new(OrderEvent::AmountBefore).decimal_len(39, 0))
+                    .modify_column(ColumnDef::new(OrderEvent::AmountAfter).decimal_len(39, 0))
+                    .to_owned(),
+            )
+            .await?;
+
+        // JSON numbers are read as doubles by most clients, the amounts are sent as strings
+        // so they are not rounded
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                CREATE OR REPLACE FUNCTION notify_order_update()
+                RETURNS TRIGGER AS $$
+                BEGIN
+                  PERFORM pg_notify('order_updates', (to_jsonb(NEW) || jsonb_build_object(
+                    'initial_amount', NEW.initial_amount::text,
+                    'filled_amount', NEW.filled_amount::text,
+                    'remaining_amount', NEW.remaining_amount::text,
+                    'price', NEW.price::text,
+                    'average_fill_price', NEW.average_fill_price::text
+                  ))::text);
+                  RETURN NEW;
+                END;
+                $$ LANGUAGE plpgsql;
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"
+                CREATE OR REPLACE FUNCTION notify_order_update()
+                RETURNS TRIGGER AS $$
+                BEGIN
+                  PERFORM pg_notify('order_updates', row_to_json(NEW)::text);
+                  RETURN NEW;
+                END;
+                $$ LANGUAGE plpgsql;
+                "#
+                .to_owned(),
+            ))
+            .await?;
+
+        // Fails if any value doesn't fit in a bigint
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(OrderEvent::Table)
+                    .modify_column(ColumnDef::new(OrderEvent::AmountBefore).big_integer())
+                    .modify_column(ColumnDef::new(OrderEvent::AmountAfter).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Trade::Table)
+                    .modify_column(ColumnDef::new(Trade::Size).big_integer())
+                    .modify_column(ColumnDef::new(Trade::Price).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .modify_column(ColumnDef::new(Order::InitialAmount).big_integer())
+                    .modify_column(ColumnDef::new(Order::FilledAmount).big_integer())
+                    .modify_column(ColumnDef::new(Order::RemainingAmount).big_integer())
+                    .modify_column(ColumnDef::new(Order::Price).big_integer())
+                    .modify_column(ColumnDef::new(Order::AverageFillPrice).big_integer())
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::market::Market;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Market::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Market::Id))
+                    .col(string_uniq(Market::MarketId))
+                    .col(string(Market::Name))
+                    .col(string(Market::Chain))
+                    .col(string(Market::BaseAsset))
+                    .col(string_null(Market::BaseSymbol))
+                    .col(integer(Market::BaseDecimals))
+                    .col(string(Market::QuoteAsset))
+                    .col(string_null(Market::QuoteSymbol))
+                    .col(integer(Market::QuoteDecimals))
+                    .col(integer(Market::PricePrecision))
+                    .col(timestamp(Market::UpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Market::Table).to_owned())
+            .await
+    }
+}
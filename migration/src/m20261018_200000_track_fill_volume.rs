@@ -0,0 +1,118 @@
+use sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+
+use crate::order::Order;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // NUMERIC(78, 0) holds any unsigned 256-bit integer, i.e. any sum of the products of
+        // two unsigned 128-bit integers the orders can reach
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .add_column(
+                        ColumnDef::new(Order::FillVolume)
+                            .decimal_len(78, 0)
+                            .not_null()
+                            .default(0),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let statements = [
+            // The volume of the fills is summed from the trades, exactly
+            r#"
+            UPDATE "order" o
+            SET fill_volume = t.fill_volume
+            FROM (
+              SELECT order_id, SUM(size * price) AS fill_volume
+              FROM trade
+              GROUP BY order_id
+            ) t
+            WHERE o.order_id = t.order_id;
+            "#,
+            // The recorded order states only know their rounded average fill price, which is
+            // the best estimate of their volume
+            r#"
+            UPDATE order_undo
+            SET state = state || jsonb_build_object(
+              'fill_volume',
+              ((state->>'average_fill_price')::numeric * (state->>'filled_amount')::numeric)::text
+            )
+            WHERE NOT state ? 'fill_volume';
+            "#,
+            r#"
+            CREATE OR REPLACE FUNCTION notify_order_update()
+            RETURNS TRIGGER AS $$
+            BEGIN
+              PERFORM pg_notify('order_updates', (to_jsonb(NEW) || jsonb_build_object(
+                'initial_amount', NEW.initial_amount::text,
+                'filled_amount', NEW.filled_amount::text,
+                'remaining_amount', NEW.remaining_amount::text,
+                'price', NEW.price::text,
+                'average_fill_price', NEW.average_fill_price::text,
+                'fill_volume', NEW.fill_volume::text
+              ))::text);
+              RETURN NEW;
+            END;
+            $$ LANGUAGE plpgsql;
+            "#,
+        ];
+        for sql in statements {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned(),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let statements = [
+            r#"
+            CREATE OR REPLACE FUNCTION notify_order_update()
+            RETURNS TRIGGER AS $$
+            BEGIN
+              PERFORM pg_notify('order_updates', (to_jsonb(NEW) || jsonb_build_object(
+                'initial_amount', NEW.initial_amount::text,
+                'filled_amount', NEW.filled_amount::text,
+                'remaining_amount', NEW.remaining_amount::text,
+                'price', NEW.price::text,
+                'average_fill_price', NEW.average_fill_price::text
+              ))::text);
+              RETURN NEW;
+            END;
+            $$ LANGUAGE plpgsql;
+            "#,
+            r#"UPDATE order_undo SET state = state - 'fill_volume' WHERE state ? 'fill_volume';"#,
+        ];
+        for sql in statements {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    sql.to_owned(),
+                ))
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Order::Table)
+                    .drop_column(Order::FillVolume)
+                    .to_owned(),
+            )
+            .await
+    }
+}
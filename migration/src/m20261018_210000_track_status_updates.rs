@@ -0,0 +1,43 @@
+use sea_orm::Statement;
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::state::State;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(State::Table)
+                    .add_column(timestamp_null(State::StatusUpdatedAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // The timestamp was overwritten by the status reports so far
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"UPDATE state SET status_updated_at = timestamp;"#.to_owned(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(State::Table)
+                    .drop_column(State::StatusUpdatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,19 @@
+use sea_orm_migration::prelude::*;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+pub enum Market {
+    Table,
+    Id,
+    MarketId,
+    Name,
+    Chain,
+    BaseAsset,
+    BaseSymbol,
+    BaseDecimals,
+    QuoteAsset,
+    QuoteSymbol,
+    QuoteDecimals,
+    PricePrecision,
+    UpdatedAt,
+}
@@ -32,7 +32,12 @@ pub enum Order {
     User,
     Asset,
     Amount,
+    InitialAmount,
+    FilledAmount,
+    RemainingAmount,
     Price,
+    AverageFillPrice,
+    FillVolume,
     Status,
     BlockNumber,
     Timestamp,
@@ -0,0 +1,28 @@
+use sea_orm_migration::{prelude::*, sea_orm::EnumIter};
+
+#[derive(DeriveIden)]
+pub struct OrderEventType;
+
+#[derive(DeriveIden, EnumIter)]
+pub enum OrderEventTypeVariants {
+    Open,
+    PartialFill,
+    Fill,
+    Cancel,
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(DeriveIden)]
+pub enum OrderEvent {
+    Table,
+    Id,
+    OrderId,
+    EventType,
+    TxId,
+    BlockNumber,
+    Timestamp,
+    AmountBefore,
+    AmountAfter,
+    Status,
+    MarketId,
+}
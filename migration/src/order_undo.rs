@@ -0,0 +1,11 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+pub enum OrderUndo {
+    Table,
+    Id,
+    MarketId,
+    OrderId,
+    BlockNumber,
+    State,
+}
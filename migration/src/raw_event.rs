@@ -0,0 +1,14 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+pub enum RawEvent {
+    Table,
+    Id,
+    MarketId,
+    BlockNumber,
+    BlockHash,
+    TransactionHash,
+    TransactionIndex,
+    LogIndex,
+    Payload,
+}
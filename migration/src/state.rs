@@ -1,4 +1,13 @@
-use sea_orm_migration::prelude::*;
+use sea_orm_migration::{prelude::*, sea_orm::EnumIter};
+
+#[derive(DeriveIden)]
+pub struct IndexerMode;
+
+#[derive(DeriveIden, EnumIter)]
+pub enum IndexerModeVariants {
+    CatchingUp,
+    Live,
+}
 
 #[derive(DeriveIden)]
 pub enum State {
@@ -7,4 +16,10 @@ pub enum State {
     MarketId,
     LatestProcessedBlock,
     Timestamp,
+    ChainHead,
+    LastEventAt,
+    Mode,
+    LastError,
+    ReconnectCount,
+    StatusUpdatedAt,
 }
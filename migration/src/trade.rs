@@ -4,6 +4,7 @@ use sea_orm_migration::{prelude::*, sea_orm::EnumIter};
 pub struct LimitType;
 
 #[derive(DeriveIden, EnumIter)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum LimitTypeVariants {
     GTC,
     IOC,
@@ -7,6 +7,8 @@ fn main() {
         .type_attribute("orderbook.types.OrderType", "#[derive(strum::FromRepr)]")
         .type_attribute("orderbook.types.OrderStatus", "#[derive(strum::FromRepr)]")
         .type_attribute("orderbook.types.LimitType", "#[derive(strum::FromRepr)]")
+        .type_attribute("orderbook.types.IndexerMode", "#[derive(strum::FromRepr)]")
+        .type_attribute("orderbook.types.AmountFormat", "#[derive(strum::FromRepr)]")
         .build_server(true)
         .file_descriptor_set_path(out_dir.join("orderbook_descriptor.bin"))
         .compile_protos(&["proto/orderbook.proto", "proto/types.proto"], &["proto"])